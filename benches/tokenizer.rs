@@ -0,0 +1,99 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use std::hint::black_box;
+use swift_tool_box::slf_writer::SlfWriter;
+use swift_tool_box::{deser, events, parser};
+
+const STEP_METRICS: &str =
+    r#"{"utime":1200,"stime":300,"maxRSS":104857600,"wcStartTime":1,"wcDuration":2000}"#;
+
+/// Encodes a synthetic SLF log shaped like an Xcode build:
+/// a main section with `targets` target sections, each holding `steps` compile steps
+/// with a command line, an occasional warning and a task metrics attachment.
+fn synthetic_log(targets: usize, steps: usize) -> Vec<u8> {
+    let mut log = SlfWriter::default();
+    log.section_start(0, "Build MyApp", 700000000.5, 700000010.25, targets);
+    for target in 0..targets {
+        let title = format!("Build target Module{target} of project MyApp");
+        log.section_start(1, &title, 700000000.5, 700000010.25, steps);
+        for step in 0..steps {
+            let file = format!("/Users/dev/MyApp/Module{target}/Sources/File{step}.swift");
+            let title = format!("Compile {file}");
+            log.section_start(2, &title, 700000000.5, 700000010.25, 0);
+            let text = format!(
+                "cd /Users/dev/MyApp\n    /usr/bin/swift-frontend -frontend -c {file} \
+                 -target arm64-apple-ios17.0 -Onone -enable-testing -g -module-name Module{target} \
+                 -o /Users/dev/Library/Developer/Xcode/DerivedData/MyApp/Build/File{step}.o\n"
+            );
+            let warnings = (0..step % 3)
+                .map(|i| format!("Variable 'x{i}' was never mutated"))
+                .collect::<Vec<_>>();
+            let messages = warnings.iter().map(|w| (w.as_str(), 1)).collect::<Vec<_>>();
+            log.section_end(Some(&text), &messages, false, Some(STEP_METRICS));
+        }
+        log.section_end(None, &[], false, None);
+    }
+    log.section_end(None, &[], false, None);
+    log.contents
+}
+
+fn count_tokens(contents: &[u8]) -> u64 {
+    parser::SliceParser::new(contents).unwrap().count() as u64
+}
+
+fn tokenizers(c: &mut Criterion) {
+    let log = synthetic_log(200, 50);
+    let contents = log.as_slice();
+
+    let mut group = c.benchmark_group("tokenizer");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(count_tokens(contents)));
+
+    group.bench_function("Parser", |b| {
+        b.iter(|| {
+            let mut parser = parser::Parser::new(black_box(contents));
+            parser.iter().count()
+        })
+    });
+    group.bench_function("SliceParser", |b| {
+        b.iter(|| {
+            parser::SliceParser::new(black_box(contents))
+                .unwrap()
+                .count()
+        })
+    });
+    group.finish();
+}
+
+fn deserializers(c: &mut Criterion) {
+    let log = synthetic_log(200, 50);
+    let contents = log.as_slice();
+
+    let mut group = c.benchmark_group("deserializer");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(count_tokens(contents)));
+
+    group.bench_function("Deserializer", |b| {
+        b.iter_batched(
+            || parser::Parser::new(contents),
+            |mut parser| {
+                let mut tokens = parser.iter().peekable();
+                deser::Deserializer::new(&mut tokens).count()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("EventReader", |b| {
+        b.iter_batched(
+            || parser::Parser::new(contents),
+            |mut parser| {
+                let mut tokens = parser.iter().peekable();
+                events::EventReader::new(&mut tokens).count()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, tokenizers, deserializers);
+criterion_main!(benches);
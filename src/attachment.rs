@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::log_class::IDEActivityLogSectionAttachment;
+
+pub const TASK_METRICS_IDENTIFIER: &str = "com.apple.dt.ActivityLogSectionAttachment.TaskMetrics";
+pub const BUILD_OPERATION_METRICS_IDENTIFIER: &str =
+    "com.apple.dt.ActivityLogSectionAttachment.BuildOperationMetrics";
+
+/// Resource usage of the process a build step ran, times are in microseconds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskMetrics {
+    /// User CPU time
+    pub utime: u64,
+    /// System CPU time
+    pub stime: u64,
+    /// Peak resident memory in bytes
+    #[serde(rename = "maxRSS")]
+    pub max_rss: u64,
+    pub wc_start_time: u64,
+    pub wc_duration: u64,
+}
+
+impl TaskMetrics {
+    /// User and system CPU time in seconds
+    pub fn cpu_time(&self) -> f64 {
+        self.utime.saturating_add(self.stime) as f64 / 1_000_000.0
+    }
+
+    /// Wall clock duration in seconds
+    pub fn wall_time(&self) -> f64 {
+        self.wc_duration as f64 / 1_000_000.0
+    }
+}
+
+/// Memory size for reports, e.g. `312.5MB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1}{}", UNITS[unit])
+}
+
+/// Counters of the whole build operation, e.g. `clangCacheHits` or `swiftCacheMisses`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildOperationMetrics {
+    #[serde(flatten)]
+    pub counters: BTreeMap<String, Value>,
+}
+
+/// Payload of an attachment decoded according to its identifier and version
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "payload", rename_all = "snake_case")]
+pub enum AttachmentPayload {
+    TaskMetrics(TaskMetrics),
+    BuildOperationMetrics(BuildOperationMetrics),
+    /// Identifier or version this crate doesn't know, the payload is kept as is
+    Unknown(Value),
+}
+
+impl IDEActivityLogSectionAttachment {
+    /// Decodes the payload of the known attachments.
+    /// Fails if a known attachment doesn't have the expected fields.
+    pub fn decode(&self) -> anyhow::Result<AttachmentPayload> {
+        let payload = &self.unknown1;
+        Ok(match (self.identifier.as_str(), self.major_version) {
+            (TASK_METRICS_IDENTIFIER, 1) => {
+                AttachmentPayload::TaskMetrics(TaskMetrics::deserialize(payload)?)
+            }
+            (BUILD_OPERATION_METRICS_IDENTIFIER, 1) => AttachmentPayload::BuildOperationMetrics(
+                BuildOperationMetrics::deserialize(payload)?,
+            ),
+            _ => AttachmentPayload::Unknown(payload.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn attachment(
+        identifier: &str,
+        major_version: u64,
+        payload: Value,
+    ) -> IDEActivityLogSectionAttachment {
+        IDEActivityLogSectionAttachment {
+            identifier: identifier.to_string(),
+            major_version,
+            minor_version: 0,
+            unknown1: payload,
+        }
+    }
+
+    #[test]
+    fn decodes_task_metrics() {
+        let payload = json!({
+            "utime": 1_500_000,
+            "stime": 500_000,
+            "maxRSS": 104_857_600,
+            "wcStartTime": 42,
+            "wcDuration": 3_000_000,
+        });
+        let decoded = attachment(TASK_METRICS_IDENTIFIER, 1, payload)
+            .decode()
+            .unwrap();
+        let AttachmentPayload::TaskMetrics(metrics) = decoded else {
+            panic!("Expected task metrics, got {decoded:?}");
+        };
+        assert_eq!(metrics.max_rss, 104_857_600);
+        assert_eq!(metrics.wc_start_time, 42);
+        assert_eq!(metrics.cpu_time(), 2.0);
+        assert_eq!(metrics.wall_time(), 3.0);
+        assert_eq!(format_bytes(metrics.max_rss), "100.0MB");
+    }
+
+    #[test]
+    fn saturates_cpu_time_of_corrupt_metrics() {
+        let metrics = TaskMetrics {
+            utime: u64::MAX,
+            stime: 1,
+            max_rss: 0,
+            wc_start_time: 0,
+            wc_duration: 0,
+        };
+        assert_eq!(metrics.cpu_time(), u64::MAX as f64 / 1_000_000.0);
+    }
+
+    #[test]
+    fn rejects_task_metrics_with_missing_fields() {
+        let payload = json!({ "utime": 1, "stime": 2 });
+        assert!(attachment(TASK_METRICS_IDENTIFIER, 1, payload)
+            .decode()
+            .is_err());
+    }
+
+    #[test]
+    fn keeps_unknown_versions_as_is() {
+        let payload = json!({ "utime": "later" });
+        let decoded = attachment(TASK_METRICS_IDENTIFIER, 2, payload.clone())
+            .decode()
+            .unwrap();
+        assert_eq!(decoded, AttachmentPayload::Unknown(payload.clone()));
+        let decoded = attachment("com.example.Other", 1, payload.clone())
+            .decode()
+            .unwrap();
+        assert_eq!(decoded, AttachmentPayload::Unknown(payload));
+    }
+}
@@ -0,0 +1,338 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::deser::XActivityLogObject;
+use crate::markdown::{escape_cell, write_table_header, ToMarkdown};
+use crate::step_kind::StepKind;
+use crate::walk::walk_sections;
+
+/// Share of cache hits among the steps of one kind in one target above which
+/// a miss of such a step is reported as unexpected
+const UNEXPECTED_MISS_HIT_RATE: f64 = 0.5;
+
+/// Steps of one kind in one target needed before misses are reported as
+/// unexpected, with fewer steps a single miss says little about the cache
+const UNEXPECTED_MISS_MIN_STEPS: usize = 4;
+
+/// Cache effectiveness of the build, durations are in seconds
+#[derive(Debug, Serialize)]
+pub struct CacheReport {
+    pub total: CacheStats,
+    pub by_target: Vec<CacheGroup>,
+    pub by_kind: Vec<CacheGroup>,
+    /// Steps that were re-run although most steps of the same kind
+    /// in the same target were fetched from cache, slowest first
+    pub unexpected_misses: Vec<CacheMiss>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub hit_rate: f64,
+    pub hit_duration: f64,
+    pub miss_duration: f64,
+    /// Estimated as the average duration of a miss of the same kind
+    /// in the same target minus the duration of the hit
+    pub time_saved: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheGroup {
+    pub name: String,
+    #[serde(flatten)]
+    pub stats: CacheStats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheMiss {
+    pub target: Option<String>,
+    pub kind: StepKind,
+    pub title: String,
+    pub duration: f64,
+}
+
+struct Step<'a> {
+    target: Option<&'a str>,
+    kind: StepKind,
+    title: &'a str,
+    duration: f64,
+    hit: bool,
+}
+
+impl CacheStats {
+    fn add(&mut self, step: &Step, time_saved: f64) {
+        if step.hit {
+            self.hits += 1;
+            self.hit_duration += step.duration;
+            self.time_saved += time_saved;
+        } else {
+            self.misses += 1;
+            self.miss_duration += step.duration;
+        }
+        self.hit_rate = self.hits as f64 / (self.hits + self.misses) as f64;
+    }
+}
+
+fn into_groups(stats: BTreeMap<String, CacheStats>) -> Vec<CacheGroup> {
+    let mut groups = stats
+        .into_iter()
+        .map(|(name, stats)| CacheGroup { name, stats })
+        .collect::<Vec<_>>();
+    groups.sort_by(|a, b| {
+        let total = |g: &CacheGroup| g.stats.hit_duration + g.stats.miss_duration;
+        total(b).total_cmp(&total(a))
+    });
+    groups
+}
+
+/// Builds the cache report over the leaf build steps of the log
+pub fn cache_report(objects: &[XActivityLogObject]) -> CacheReport {
+    let steps = walk_sections(objects)
+        .filter(|n| n.is_step() && n.section.sub_sections.is_empty())
+        .map(|node| Step {
+            target: node.target_name(),
+            kind: node.section.step_info().kind,
+            title: &node.section.title,
+            duration: node.section.duration().as_seconds_f64(),
+            hit: node.section.was_fetched_from_cache,
+        })
+        .collect::<Vec<_>>();
+
+    // Per target and kind: (hits, misses, summed miss duration)
+    let mut per_target_kind = HashMap::<(Option<&str>, &StepKind), (usize, usize, f64)>::new();
+    let mut per_kind_misses = HashMap::<&StepKind, (usize, f64)>::new();
+    for step in steps.iter() {
+        let entry = per_target_kind
+            .entry((step.target, &step.kind))
+            .or_default();
+        if step.hit {
+            entry.0 += 1;
+        } else {
+            entry.1 += 1;
+            entry.2 += step.duration;
+            let kind_entry = per_kind_misses.entry(&step.kind).or_default();
+            kind_entry.0 += 1;
+            kind_entry.1 += step.duration;
+        }
+    }
+    let average_miss = |step: &Step| {
+        let (_, misses, duration) = per_target_kind[&(step.target, &step.kind)];
+        if misses > 0 {
+            return Some(duration / misses as f64);
+        }
+        per_kind_misses
+            .get(&step.kind)
+            .map(|(misses, duration)| duration / *misses as f64)
+    };
+
+    let mut total = CacheStats::default();
+    let mut by_target = BTreeMap::<String, CacheStats>::new();
+    let mut by_kind = BTreeMap::<String, CacheStats>::new();
+    let mut unexpected_misses = vec![];
+    for step in steps.iter() {
+        let time_saved = match step.hit {
+            true => average_miss(step)
+                .map(|average| (average - step.duration).max(0.0))
+                .unwrap_or(0.0),
+            false => 0.0,
+        };
+        total.add(step, time_saved);
+        by_target
+            .entry(step.target.unwrap_or("<no target>").to_string())
+            .or_default()
+            .add(step, time_saved);
+        by_kind
+            .entry(step.kind.to_string())
+            .or_default()
+            .add(step, time_saved);
+
+        let (hits, misses, _) = per_target_kind[&(step.target, &step.kind)];
+        if !step.hit
+            && hits + misses >= UNEXPECTED_MISS_MIN_STEPS
+            && hits as f64 / (hits + misses) as f64 > UNEXPECTED_MISS_HIT_RATE
+        {
+            unexpected_misses.push(CacheMiss {
+                target: step.target.map(str::to_string),
+                kind: step.kind.clone(),
+                title: step.title.to_string(),
+                duration: step.duration,
+            });
+        }
+    }
+    unexpected_misses.sort_by(|a, b| b.duration.total_cmp(&a.duration));
+
+    CacheReport {
+        total,
+        by_target: into_groups(by_target),
+        by_kind: into_groups(by_kind),
+        unexpected_misses,
+    }
+}
+
+fn write_stats_row(f: &mut fmt::Formatter<'_>, name: &str, stats: &CacheStats) -> fmt::Result {
+    writeln!(
+        f,
+        "  {:<32} {:>6} {:>6} {:>6.1}% {:>10.2}s {:>10.2}s",
+        name,
+        stats.hits,
+        stats.misses,
+        stats.hit_rate * 100.0,
+        stats.miss_duration,
+        stats.time_saved
+    )
+}
+
+impl fmt::Display for CacheReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, groups) in [("Target", &self.by_target), ("Step kind", &self.by_kind)] {
+            writeln!(
+                f,
+                "  {:<32} {:>6} {:>6} {:>7} {:>11} {:>11}",
+                name, "hits", "misses", "rate", "miss time", "saved"
+            )?;
+            for group in groups {
+                write_stats_row(f, &group.name, &group.stats)?;
+            }
+            writeln!(f)?;
+        }
+        write_stats_row(f, "Total", &self.total)?;
+
+        writeln!(f, "\nUnexpected misses ({}):", self.unexpected_misses.len())?;
+        for miss in self.unexpected_misses.iter() {
+            writeln!(
+                f,
+                "  {:>8.2}s  [{}] {}",
+                miss.duration,
+                miss.target.as_deref().unwrap_or("<no target>"),
+                miss.title
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn write_stats_cells(f: &mut fmt::Formatter<'_>, name: &str, stats: &CacheStats) -> fmt::Result {
+    writeln!(
+        f,
+        "| {name} | {} | {} | {:.1}% | {:.2}s | {:.2}s |",
+        stats.hits,
+        stats.misses,
+        stats.hit_rate * 100.0,
+        stats.miss_duration,
+        stats.time_saved
+    )
+}
+
+impl ToMarkdown for CacheReport {
+    fn fmt_markdown(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, groups) in [("Target", &self.by_target), ("Step kind", &self.by_kind)] {
+            write_table_header(f, &[name, "Hits", "Misses", "Rate", "Miss time", "Saved"])?;
+            for group in groups {
+                write_stats_cells(f, &escape_cell(&group.name), &group.stats)?;
+            }
+            write_stats_cells(f, "**Total**", &self.total)?;
+            writeln!(f)?;
+        }
+
+        writeln!(
+            f,
+            "**Unexpected misses ({})**\n",
+            self.unexpected_misses.len()
+        )?;
+        if self.unexpected_misses.is_empty() {
+            return Ok(());
+        }
+        write_table_header(f, &["Duration", "Target", "Step"])?;
+        for miss in self.unexpected_misses.iter() {
+            writeln!(
+                f,
+                "| {:.2}s | {} | {} |",
+                miss.duration,
+                escape_cell(miss.target.as_deref().unwrap_or("")),
+                escape_cell(&miss.title)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_class::IDEActivityLogSection;
+
+    fn step(target: &str, file: &str, seconds: i64, hit: bool) -> IDEActivityLogSection {
+        let signature = format!("SwiftCompile normal arm64 /src/{file}");
+        let mut section = IDEActivityLogSection::test_step_in(target, &signature, seconds);
+        section.was_fetched_from_cache = hit;
+        section
+    }
+
+    #[test]
+    fn estimates_time_saved_by_hits() {
+        let report = cache_report(&[IDEActivityLogSection::test_build(vec![
+            step("App", "A.swift", 1, true),
+            step("App", "B.swift", 5, false),
+            step("App", "C.swift", 3, false),
+            // No misses in the target, the misses of the kind elsewhere are used
+            step("Kit", "K.swift", 1, true),
+        ])
+        .into()]);
+        assert_eq!((report.total.hits, report.total.misses), (2, 2));
+        assert_eq!(report.total.hit_rate, 0.5);
+        assert_eq!(report.total.miss_duration, 8.0);
+        assert_eq!(report.total.time_saved, 6.0);
+
+        let app = &report.by_target[0];
+        assert_eq!(app.name, "App");
+        assert_eq!(app.stats.time_saved, 3.0);
+        assert_eq!(report.by_kind[0].name, "SwiftCompile");
+        assert!(report.unexpected_misses.is_empty());
+    }
+
+    #[test]
+    fn reports_misses_among_mostly_cached_steps() {
+        let report = cache_report(&[IDEActivityLogSection::test_build(vec![
+            step("App", "A.swift", 1, true),
+            step("App", "B.swift", 1, true),
+            step("App", "C.swift", 1, true),
+            step("App", "D.swift", 4, false),
+            step("App", "E.swift", 6, false),
+        ])
+        .into()]);
+        let misses = report
+            .unexpected_misses
+            .iter()
+            .map(|m| m.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            misses,
+            [
+                "SwiftCompile normal arm64 /src/E.swift",
+                "SwiftCompile normal arm64 /src/D.swift"
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_misses_of_small_or_evenly_split_groups() {
+        let few = cache_report(&[IDEActivityLogSection::test_build(vec![
+            step("App", "A.swift", 1, true),
+            step("App", "B.swift", 1, true),
+            step("App", "C.swift", 1, false),
+        ])
+        .into()]);
+        assert!(few.unexpected_misses.is_empty());
+
+        let even = cache_report(&[IDEActivityLogSection::test_build(vec![
+            step("App", "A.swift", 1, true),
+            step("App", "B.swift", 1, true),
+            step("App", "C.swift", 1, false),
+            step("App", "D.swift", 1, false),
+        ])
+        .into()]);
+        assert!(even.unexpected_misses.is_empty());
+    }
+}
@@ -1,12 +1,15 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use regex::Regex;
 use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
+use swift_tool_box::select::Selector;
 
 #[derive(Debug, Clone)]
 pub enum OutputFile {
     Json(PathBuf),
     Csv(PathBuf),
+    Ndjson(PathBuf),
 }
 
 impl FromStr for OutputFile {
@@ -18,23 +21,208 @@ impl FromStr for OutputFile {
         match extension {
             Some("json") => Ok(OutputFile::Json(path.into())),
             Some("csv") => Ok(OutputFile::Csv(path.into())),
+            Some("ndjson") | Some("jsonl") => Ok(OutputFile::Ndjson(path.into())),
             _ => Err(String::from(
-                "The output file must have a .json or .csv extension",
+                "The output file must have a .json, .ndjson, .jsonl or .csv extension",
             )),
         }
     }
 }
 
+impl OutputFile {
+    /// Output file for one of several logs: `result.json` becomes `result-<id>.json`
+    pub fn for_log(&self, id: &str) -> OutputFile {
+        let with_id = |path: &PathBuf| {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let extension = path.extension().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!("{stem}-{id}.{extension}"))
+        };
+        match self {
+            OutputFile::Json(path) => OutputFile::Json(with_id(path)),
+            OutputFile::Csv(path) => OutputFile::Csv(with_id(path)),
+            OutputFile::Ndjson(path) => OutputFile::Ndjson(with_id(path)),
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 #[clap(
     version = "1.0",
     author = "Andrey <suzukenobi@gmail.com>",
-    about = "Converts .xcactivitylog files to .json or .csv format"
+    about = "Converts .xcactivitylog files to .json, .ndjson or .csv format",
+    subcommand_negates_reqs = true
 )]
 pub struct Args {
-    #[clap(short, long, value_name = "FILE", value_parser =clap::value_parser!(PathBuf))]
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// An .xcactivitylog file or a DerivedData logs directory with LogStoreManifest.plist
+    #[clap(short, long, value_name = "FILE", value_parser =clap::value_parser!(PathBuf), required = true)]
+    pub input: Option<PathBuf>,
+
+    #[clap(short, long, value_name = "FILE", value_parser = clap::value_parser!(OutputFile), required = true)]
+    pub output: Option<OutputFile>,
+
+    /// Convert every log of the input directory instead of the newest one.
+    /// Each output file name gets the log identifier appended.
+    #[clap(long)]
+    pub all: bool,
+
+    /// Only write the nodes matching the selector, e.g.
+    /// `section[title~="Compile"][duration>2s]/message[severity=error]`.
+    /// Nodes nested in a matched node are written as part of it. Nodes are matched
+    /// before --redact applies. Only .json and .ndjson output is supported.
+    #[clap(long, value_name = "SELECTOR")]
+    pub select: Option<Selector>,
+
+    /// Replace user and machine names, home directories, DerivedData hashes, UUIDs
+    /// and credentials in environment variables with pseudonyms before writing the output.
+    /// Texts change length, so the ranges of messages keep pointing into the original text,
+    /// use `section_text` of the messages instead.
+    #[clap(long)]
+    pub redact: bool,
+
+    /// Also replace matches of this regular expression, can be repeated. Implies --redact.
+    #[clap(long, value_name = "REGEX")]
+    pub redact_pattern: Vec<Regex>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compares two builds: added and removed steps, duration and cache changes, new and fixed issues
+    Diff(DiffArgs),
+    /// Reports cache hits and misses per target and step kind and the time they saved
+    Cache(ReportArgs),
+    /// Ranks functions and expressions that were slow to type-check
+    TypeCheck(ReportArgs),
+    /// Merges the `-debug-time-function-bodies` output of all Swift compile steps
+    FunctionBodies(PlainReportArgs),
+    /// Prints the hierarchy of sections with their durations and issues
+    Tree(TreeArgs),
+    /// Prints a readable, colorized transcript of the build
+    Render(RenderArgs),
+    /// Explains a failed build: the first error, the step that reported it and its output
+    WhyFailed(PlainReportArgs),
+    /// Summarizes the build per target: timings, step kinds, cache hits and issues
+    Summary(ReportArgs),
+    /// Finds the chain of steps that determined the build time and how parallel the build was
+    CriticalPath(ReportArgs),
+    /// Ranks compile and link steps by peak memory and CPU time and flags likely swapping
+    Resources(ReportArgs),
+    /// Lists the compiler flags per target, inconsistencies between targets and risky release settings
+    AuditFlags(PlainReportArgs),
+    /// Writes a clang compilation database for the compile steps of the log
+    CompileCommands(CompileCommandsArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct CompileCommandsArgs {
+    /// An .xcactivitylog file or a DerivedData logs directory, the newest log is used
+    #[clap(value_name = "INPUT")]
+    pub input: PathBuf,
+
+    #[clap(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = "compile_commands.json"
+    )]
+    pub output: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct TreeArgs {
+    /// An .xcactivitylog file or a DerivedData logs directory, the newest log is used
+    #[clap(value_name = "INPUT")]
     pub input: PathBuf,
 
-    #[clap(short, long, value_name = "FILE", value_parser = clap::value_parser!(OutputFile))]
-    pub output: OutputFile,
+    /// Deepest level to show, the build log being level 0
+    #[clap(long)]
+    pub depth: Option<usize>,
+
+    /// Hide sections shorter than this many seconds
+    #[clap(long, value_name = "SECONDS")]
+    pub min_duration: Option<f64>,
+
+    /// Show only sections whose title contains this text, with their parents and children
+    #[clap(long, value_name = "TEXT")]
+    pub filter: Option<String>,
+
+    #[clap(long, value_enum, default_value_t = PlainReportFormat::Text)]
+    pub format: PlainReportFormat,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct RenderArgs {
+    /// An .xcactivitylog file or a DerivedData logs directory, the newest log is used
+    #[clap(value_name = "INPUT")]
+    pub input: PathBuf,
+
+    /// Show every step and test, repeat to also show notes and the output of failed steps
+    #[clap(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Show only errors and the result of the build
+    #[clap(short, long)]
+    pub quiet: bool,
+
+    #[clap(long, value_enum, default_value_t = ColorMode::Auto)]
+    pub color: ColorMode,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum ColorMode {
+    /// Colorize when writing to a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+/// Arguments of the commands that analyze a single log
+#[derive(clap::Args, Debug)]
+pub struct ReportArgs {
+    /// An .xcactivitylog file or a DerivedData logs directory, the newest log is used
+    #[clap(value_name = "INPUT")]
+    pub input: PathBuf,
+
+    #[clap(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+/// Arguments of reports without a Markdown form
+#[derive(clap::Args, Debug)]
+pub struct PlainReportArgs {
+    /// An .xcactivitylog file or a DerivedData logs directory, the newest log is used
+    #[clap(value_name = "INPUT")]
+    pub input: PathBuf,
+
+    #[clap(long, value_enum, default_value_t = PlainReportFormat::Text)]
+    pub format: PlainReportFormat,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct DiffArgs {
+    /// Log of the reference build, e.g. of the main branch
+    #[clap(value_name = "BASE")]
+    pub base: PathBuf,
+
+    /// Log of the build to compare against the reference
+    #[clap(value_name = "HEAD")]
+    pub head: PathBuf,
+
+    #[clap(long, value_enum, default_value_t = PlainReportFormat::Text)]
+    pub format: PlainReportFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Markdown,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum PlainReportFormat {
+    Text,
+    Json,
 }
@@ -0,0 +1,257 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Command line a build step ran, as Xcode prints it in the step text
+/// after the signature of the step:
+///
+/// ```text
+/// CompileC /Users/jdoe/Build/main.o /Users/jdoe/Developer/MyApp/main.c normal arm64 c ...
+///     cd /Users/jdoe/Developer/MyApp
+///     export LANG\=en_US.US-ASCII
+///     /usr/bin/clang -x c -c /Users/jdoe/Developer/MyApp/main.c -o main.o
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildCommand {
+    /// Directory of the preceding `cd`, if any
+    pub cwd: Option<String>,
+    pub executable: String,
+    pub args: Vec<String>,
+    /// Variables set by the preceding `export` lines
+    pub env: BTreeMap<String, String>,
+}
+
+impl BuildCommand {
+    /// Extracts the first command of a step text, `None` if the text has no command line.
+    /// Lines before a `cd` are skipped unless they run an absolute path, the first line
+    /// is the signature of the step. Commands run through an Xcode builtin like
+    /// `builtin-SwiftDriver -- swiftc ...` are unwrapped to the command after `--`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut cwd = None;
+        let mut env = BTreeMap::new();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let mut words = split_command_line(line).into_iter();
+            let Some(executable) = words.next() else {
+                continue;
+            };
+            match executable.as_str() {
+                "cd" => cwd = words.next(),
+                "export" => env.extend(words.filter_map(|assignment| {
+                    let (name, value) = assignment.split_once('=')?;
+                    Some((name.to_string(), value.to_string()))
+                })),
+                _ if cwd.is_none() && !executable.starts_with('/') => continue,
+                _ => {
+                    let mut args = words.collect::<Vec<_>>();
+                    let mut executable = executable;
+                    if executable.starts_with("builtin-") {
+                        if let Some(separator) = args.iter().position(|a| a == "--") {
+                            let mut wrapped = args.split_off(separator).into_iter().skip(1);
+                            if let Some(wrapped_executable) = wrapped.next() {
+                                executable = wrapped_executable;
+                                args = wrapped.collect();
+                            }
+                        }
+                    }
+                    return Some(BuildCommand {
+                        cwd,
+                        executable,
+                        args,
+                        env,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Executable followed by the arguments
+    pub fn argv(&self) -> Vec<String> {
+        std::iter::once(self.executable.clone())
+            .chain(self.args.iter().cloned())
+            .collect()
+    }
+
+    /// Argument following the first occurrence of `flag`, e.g. the output of `-o`
+    pub fn argument_after(&self, flag: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .position(|a| a == flag)
+            .and_then(|i| self.args.get(i + 1))
+            .map(String::as_str)
+    }
+
+    /// Arguments following every occurrence of `flag`, e.g. all `-primary-file`s
+    pub fn arguments_after<'a>(&'a self, flag: &'a str) -> impl Iterator<Item = &'a str> {
+        self.args
+            .windows(2)
+            .filter(move |w| w[0] == flag)
+            .map(|w| w[1].as_str())
+    }
+
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.args.iter().any(|a| a == flag)
+    }
+}
+
+/// Command line that can be pasted into a shell to run the step again
+impl fmt::Display for BuildCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(cwd) = &self.cwd {
+            write!(f, "cd {} && ", shell_quote(cwd))?;
+        }
+        for (name, value) in self.env.iter() {
+            write!(f, "{name}={} ", shell_quote(value))?;
+        }
+        let argv = self.argv();
+        let quoted = argv.iter().map(|a| shell_quote(a)).collect::<Vec<_>>();
+        write!(f, "{}", quoted.join(" "))
+    }
+}
+
+/// Quotes an argument for a POSIX shell if it contains anything but safe characters
+pub fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Splits a command line the way a POSIX shell would, honouring quotes and backslash escapes
+pub fn split_command_line(line: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                in_word = true;
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            '\'' => {
+                in_word = true;
+                current.extend(chars.by_ref().take_while(|c| *c != '\''));
+            }
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some(next @ ('"' | '\\' | '$' | '`')) => current.push(next),
+                            Some(next) => {
+                                current.push('\\');
+                                current.push(next);
+                            }
+                            None => current.push('\\'),
+                        },
+                        c => current.push(c),
+                    }
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        args.push(current);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPILE_C_TEXT: &str = "CompileC /Users/jdoe/Build/util.o /Users/jdoe/Developer/MyApp/util.c normal arm64 c com.apple.compilers.llvm.clang.1_0.compiler (in target 'MyApp' from project 'MyApp')
+    cd /Users/jdoe/Developer/MyApp
+    export LANG\\=en_US.US-ASCII
+    /Applications/Xcode.app/Contents/Developer/Toolchains/XcodeDefault.xctoolchain/usr/bin/clang -x c -DDEBUG\\=1 -I/Users/jdoe/My\\ Headers \"-I/Users/jdoe/Other Headers\" -c /Users/jdoe/Developer/MyApp/util.c -o /Users/jdoe/Build/util.o
+";
+
+    const SWIFT_DRIVER_TEXT: &str = "SwiftDriver MyApp normal arm64 com.apple.xcode.tools.swift.compiler (in target 'MyApp' from project 'MyApp')
+    cd /Users/jdoe/Developer/MyApp
+    builtin-SwiftDriver -- /Applications/Xcode.app/Contents/Developer/Toolchains/XcodeDefault.xctoolchain/usr/bin/swiftc -module-name MyApp /Users/jdoe/Developer/MyApp/App.swift
+";
+
+    #[test]
+    fn parses_command_after_signature() {
+        let command = BuildCommand::parse(COMPILE_C_TEXT).unwrap();
+        assert_eq!(command.cwd.as_deref(), Some("/Users/jdoe/Developer/MyApp"));
+        assert!(command.executable.ends_with("/usr/bin/clang"));
+        assert_eq!(
+            command.env.get("LANG").map(String::as_str),
+            Some("en_US.US-ASCII")
+        );
+        assert!(command.has_flag("-DDEBUG=1"));
+        assert!(command.has_flag("-I/Users/jdoe/My Headers"));
+        assert!(command.has_flag("-I/Users/jdoe/Other Headers"));
+        assert_eq!(
+            command.argument_after("-o"),
+            Some("/Users/jdoe/Build/util.o")
+        );
+    }
+
+    #[test]
+    fn unwraps_builtin_driver() {
+        let command = BuildCommand::parse(SWIFT_DRIVER_TEXT).unwrap();
+        assert!(command.executable.ends_with("/usr/bin/swiftc"));
+        assert_eq!(command.args[..2], ["-module-name", "MyApp"]);
+    }
+
+    #[test]
+    fn parses_absolute_command_without_cd() {
+        let command = BuildCommand::parse("Ld MyApp normal\n    /usr/bin/ld -o out").unwrap();
+        assert_eq!(command.cwd, None);
+        assert_eq!(command.argv(), ["/usr/bin/ld", "-o", "out"]);
+    }
+
+    #[test]
+    fn signature_alone_is_no_command() {
+        assert_eq!(BuildCommand::parse("CompileC /a.o /a.c normal arm64"), None);
+        assert_eq!(BuildCommand::parse(""), None);
+    }
+
+    #[test]
+    fn splits_like_a_shell() {
+        let cases: &[(&str, &[&str])] = &[
+            ("a b  c", &["a", "b", "c"]),
+            ("My\\ Headers/a.h", &["My Headers/a.h"]),
+            ("export FOO\\=bar", &["export", "FOO=bar"]),
+            (
+                "'single quoted' \"double quoted\"",
+                &["single quoted", "double quoted"],
+            ),
+            ("\"escaped \\\" quote\" '\\n'", &["escaped \" quote", "\\n"]),
+            ("-DNAME=\"\"", &["-DNAME="]),
+            ("''", &[""]),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(split_command_line(line), *expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn display_quotes_for_the_shell() {
+        let command = BuildCommand::parse(COMPILE_C_TEXT).unwrap();
+        let line = command.to_string();
+        assert!(line.starts_with("cd /Users/jdoe/Developer/MyApp && LANG=en_US.US-ASCII "));
+        assert!(line.contains("'-I/Users/jdoe/My Headers'"));
+        let (_, rest) = line.split_once(" && ").unwrap();
+        let words = split_command_line(rest);
+        assert_eq!(words[0], "LANG=en_US.US-ASCII");
+        assert_eq!(words[1..], command.argv());
+    }
+}
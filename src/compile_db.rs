@@ -0,0 +1,201 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::deser::XActivityLogObject;
+use crate::log_class::IDEActivityLogSection;
+use crate::step_kind::StepKind;
+use crate::walk::walk_sections;
+
+/// Entry of a clang compilation database (`compile_commands.json`)
+#[derive(Debug, Clone, Serialize)]
+pub struct CompileCommand {
+    pub directory: String,
+    pub arguments: Vec<String>,
+    pub file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+/// Kinds of steps that compile sources, in order of preference when several
+/// steps compile the same file. Per-file compiler invocations come first,
+/// the driver and the legacy per-module step only fill in files they don't cover.
+const COMPILE_STEP_KINDS: [StepKind; 4] = [
+    StepKind::CompileC,
+    StepKind::SwiftCompile,
+    StepKind::SwiftDriver,
+    StepKind::CompileSwiftSources,
+];
+
+/// Extensions of the sources listed by commands that don't name them with a flag.
+/// Swift steps only compile Swift sources, C-family files they list (e.g. passed
+/// through to clang by the driver) come from their own `CompileC` steps.
+const SWIFT_SOURCE_EXTENSIONS: [&str; 1] = ["swift"];
+const C_SOURCE_EXTENSIONS: [&str; 6] = ["c", "m", "mm", "cpp", "cc", "cxx"];
+
+fn is_source_file(argument: &str, extensions: &[&str]) -> bool {
+    !argument.starts_with('-')
+        && Path::new(argument)
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| extensions.contains(&e))
+}
+
+fn compile_step_priority(section: &IDEActivityLogSection) -> Option<usize> {
+    let kind = section.step_info().kind;
+    COMPILE_STEP_KINDS.iter().position(|k| *k == kind)
+}
+
+fn commands_of_step(section: &IDEActivityLogSection) -> Vec<CompileCommand> {
+    let Some(command) = section.build_command() else {
+        return vec![];
+    };
+    let Some(directory) = command.cwd.clone() else {
+        return vec![];
+    };
+    let output = command.argument_after("-o").map(str::to_string);
+    let listed_sources = |extensions: &[&str]| {
+        command
+            .args
+            .iter()
+            .filter(|a| is_source_file(a, extensions))
+            .map(|file| (file.clone(), output.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    // Frontend jobs name their files with `-primary-file`, clang with `-c`,
+    // the Swift driver and whole-module jobs just list all sources of the module
+    let files = if section.step_info().kind == StepKind::CompileC {
+        match command.argument_after("-c").filter(|f| !f.starts_with('-')) {
+            Some(file) => vec![(file.to_string(), output.clone())],
+            None => listed_sources(&C_SOURCE_EXTENSIONS),
+        }
+    } else {
+        // Batch mode jobs compile several primary files, each with its own `-o` in the same order
+        let mut outputs = command.arguments_after("-o");
+        let primary_files = command
+            .arguments_after("-primary-file")
+            .map(|file| (file.to_string(), outputs.next().map(str::to_string)))
+            .collect::<Vec<_>>();
+        if primary_files.is_empty() {
+            listed_sources(&SWIFT_SOURCE_EXTENSIONS)
+        } else {
+            primary_files
+        }
+    };
+
+    let arguments = command.argv();
+    files
+        .into_iter()
+        .map(|(file, output)| CompileCommand {
+            directory: directory.clone(),
+            arguments: arguments.clone(),
+            file,
+            output,
+        })
+        .collect()
+}
+
+/// Compilation database of all compile steps, one entry per source file
+pub fn compile_commands(objects: &[XActivityLogObject]) -> Vec<CompileCommand> {
+    let mut steps = walk_sections(objects)
+        .filter_map(|node| Some((compile_step_priority(node.section)?, node.section)))
+        .collect::<Vec<_>>();
+    // Stable, so steps of the same kind keep the build order
+    steps.sort_by_key(|(priority, _)| *priority);
+
+    let mut seen = HashSet::new();
+    let mut commands = steps
+        .into_iter()
+        .flat_map(|(_, section)| commands_of_step(section))
+        .filter(|command| {
+            let path = Path::new(&command.directory).join(&command.file);
+            seen.insert(path)
+        })
+        .collect::<Vec<_>>();
+    commands.sort_by(|a, b| a.file.cmp(&b.file));
+    commands
+}
+
+pub fn write_compile_commands(commands: &[CompileCommand], path: &PathBuf) -> anyhow::Result<()> {
+    let file = File::create(path)?;
+    serde_json::to_writer_pretty(BufWriter::new(file), commands)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Step of the `App` target running `command` in `/src`
+    fn step(signature: &str, command: &str) -> IDEActivityLogSection {
+        let mut step = IDEActivityLogSection::test_step_in("App", signature, 1);
+        step.text = Some(format!("{}\n    cd /src\n    {command}\n", step.signature));
+        step
+    }
+
+    #[test]
+    fn builds_database_from_compile_steps() {
+        let commands = compile_commands(&[IDEActivityLogSection::test_build(vec![
+            step(
+                "CompileC /Build/util.o /src/util.c normal arm64 c com.apple.compilers.llvm.clang.1_0.compiler",
+                "export LANG\\=en_US.US-ASCII\n    /usr/bin/clang -x c -c /src/util.c -o /Build/util.o",
+            ),
+            step(
+                "SwiftCompile normal arm64 /src/App.swift",
+                "builtin-swiftTaskExecution -- /usr/bin/swift-frontend -frontend -c /src/Other.swift -primary-file /src/App.swift -o /Build/App.o",
+            ),
+            step(
+                "SwiftDriver App normal arm64 com.apple.xcode.tools.swift.compiler",
+                "builtin-SwiftDriver -- /usr/bin/swiftc -module-name App /src/App.swift /src/Other.swift /src/Bridge.m",
+            ),
+        ]).into()]);
+        let files = commands.iter().map(|c| c.file.as_str()).collect::<Vec<_>>();
+        // Bridge.m has no CompileC step, the Swift driver doesn't compile it
+        assert_eq!(files, ["/src/App.swift", "/src/Other.swift", "/src/util.c"]);
+
+        let app = &commands[0];
+        assert_eq!(app.directory, "/src");
+        assert_eq!(app.arguments[0], "/usr/bin/swift-frontend");
+        assert_eq!(app.output.as_deref(), Some("/Build/App.o"));
+        // Not compiled by a per-file step, so the driver fills it in
+        assert_eq!(commands[1].arguments[0], "/usr/bin/swiftc");
+        assert_eq!(
+            commands[2].arguments,
+            [
+                "/usr/bin/clang",
+                "-x",
+                "c",
+                "-c",
+                "/src/util.c",
+                "-o",
+                "/Build/util.o"
+            ]
+        );
+    }
+
+    #[test]
+    fn pairs_primary_files_with_their_outputs() {
+        let commands = compile_commands(&[IDEActivityLogSection::test_build(vec![step(
+            "SwiftCompile normal arm64 Compiling\\ A.swift,\\ B.swift,\\ C.swift",
+            "builtin-swiftTaskExecution -- /usr/bin/swift-frontend -frontend -c -primary-file /src/A.swift -primary-file /src/B.swift /src/D.swift -primary-file /src/C.swift -emit-module-path /Build/App.swiftmodule -o /Build/A.o -o /Build/B.o -o /Build/C.o",
+        )]).into()]);
+        let outputs = commands
+            .iter()
+            .map(|c| (c.file.as_str(), c.output.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outputs,
+            [
+                ("/src/A.swift", Some("/Build/A.o")),
+                ("/src/B.swift", Some("/Build/B.o")),
+                ("/src/C.swift", Some("/Build/C.o")),
+            ]
+        );
+        assert!(commands
+            .iter()
+            .all(|c| c.arguments == commands[0].arguments));
+    }
+}
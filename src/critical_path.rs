@@ -0,0 +1,570 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use time::OffsetDateTime;
+
+use crate::attachment::{format_bytes, TaskMetrics};
+use crate::deser::XActivityLogObject;
+use crate::markdown::{escape_cell, write_table_header, ToMarkdown};
+use crate::walk::{target_section_name, walk_sections};
+
+/// Number of intervals the build is split into for the parallelism timeline
+const PARALLELISM_SAMPLES: usize = 20;
+
+/// A step counts as waiting for another one if it started at most this many
+/// seconds before the other one ended, the recorded times are not exact
+const START_TOLERANCE: f64 = 0.001;
+
+/// Chain of steps that determined the wall time of the build and how well the
+/// build used parallelism. The log has no dependency information, so a step
+/// is assumed to wait for the step that finished last before it started.
+/// Durations and offsets are in seconds from the start of the build.
+#[derive(Debug, Serialize)]
+pub struct CriticalPathReport {
+    pub wall_time: f64,
+    /// Sum of the durations of all steps
+    pub step_time: f64,
+    /// Step time divided by wall time
+    pub average_parallelism: f64,
+    pub max_parallelism: usize,
+    /// From the first to the last step of the build
+    pub critical_path: Vec<CriticalStep>,
+    /// Time of the critical path spent in steps, the rest is spent waiting
+    pub critical_path_step_time: f64,
+    /// Share of the critical path per target, longest first
+    pub by_target: Vec<TargetShare>,
+    /// Time span of each target section, in order of start
+    pub target_spans: Vec<TargetSpan>,
+    pub timeline: Vec<ParallelismSample>,
+    /// Seconds during which the given number of steps were running
+    pub concurrency: Vec<ConcurrencyLevel>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CriticalStep {
+    pub target: Option<String>,
+    pub title: String,
+    pub start: f64,
+    pub duration: f64,
+    /// Time between the end of the previous step of the path and the start of this one
+    pub wait: f64,
+    /// User and system CPU time, from the task metrics of newer Xcode versions
+    pub cpu_time: Option<f64>,
+    /// Peak resident memory in bytes
+    pub max_rss: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TargetShare {
+    pub target: String,
+    pub steps: usize,
+    pub duration: f64,
+    /// Share of the wall time of the build
+    pub share: f64,
+}
+
+/// Targets with a long span but little parallelism inside it build their
+/// steps one after the other and are candidates for splitting
+#[derive(Debug, Serialize)]
+pub struct TargetSpan {
+    pub target: String,
+    pub start: f64,
+    pub end: f64,
+    /// Sum of the durations of the steps of the target
+    pub step_time: f64,
+    /// Step time divided by the span of the target
+    pub parallelism: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParallelismSample {
+    pub start: f64,
+    pub end: f64,
+    /// Average number of steps running during the interval
+    pub parallelism: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConcurrencyLevel {
+    pub steps: usize,
+    pub duration: f64,
+}
+
+struct Step<'a> {
+    target: Option<&'a str>,
+    title: &'a str,
+    start: f64,
+    end: f64,
+    metrics: Option<TaskMetrics>,
+}
+
+fn seconds_between(from: OffsetDateTime, to: OffsetDateTime) -> f64 {
+    (to - from).as_seconds_f64()
+}
+
+/// Walks back from the step that finished last, each time to the step
+/// that finished last before the current one started
+fn critical_path(steps: &[Step]) -> Vec<usize> {
+    let mut by_end = (0..steps.len()).collect::<Vec<_>>();
+    by_end.sort_by(|a, b| steps[*a].end.total_cmp(&steps[*b].end));
+
+    // Positions in `by_end`, the predecessor always comes earlier so the walk ends
+    let mut path = vec![];
+    let mut current = by_end.len().checked_sub(1);
+    while let Some(position) = current {
+        let i = by_end[position];
+        path.push(i);
+        let start = steps[i].start;
+        let before =
+            by_end[..position].partition_point(|j| steps[*j].end <= start + START_TOLERANCE);
+        current = before.checked_sub(1);
+    }
+    path.reverse();
+    path
+}
+
+fn timeline(steps: &[Step], wall_time: f64) -> Vec<ParallelismSample> {
+    if wall_time <= 0.0 {
+        return vec![];
+    }
+    let length = wall_time / PARALLELISM_SAMPLES as f64;
+    (0..PARALLELISM_SAMPLES)
+        .map(|i| {
+            let start = i as f64 * length;
+            let end = start + length;
+            let busy = steps
+                .iter()
+                .map(|s| (s.end.min(end) - s.start.max(start)).max(0.0))
+                .sum::<f64>();
+            ParallelismSample {
+                start,
+                end,
+                parallelism: busy / length,
+            }
+        })
+        .collect()
+}
+
+fn concurrency(steps: &[Step]) -> Vec<ConcurrencyLevel> {
+    // Ends sort before starts at the same time so that back to back steps don't overlap,
+    // steps without a duration are skipped since their end would come before their start
+    let mut events = steps
+        .iter()
+        .filter(|s| s.end > s.start)
+        .flat_map(|s| [(s.start, 1), (s.end, -1)])
+        .collect::<Vec<(f64, i64)>>();
+    events.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    let mut durations = BTreeMap::<usize, f64>::new();
+    let mut running = 0i64;
+    let mut previous = events.first().map(|e| e.0).unwrap_or_default();
+    for (time, change) in events {
+        *durations.entry(running as usize).or_default() += time - previous;
+        running += change;
+        previous = time;
+    }
+    durations
+        .into_iter()
+        .filter(|(_, duration)| *duration > 0.0)
+        .map(|(steps, duration)| ConcurrencyLevel { steps, duration })
+        .collect()
+}
+
+/// Computes the critical path over the leaf steps of the log
+pub fn critical_path_report(objects: &[XActivityLogObject]) -> CriticalPathReport {
+    let nodes = walk_sections(objects)
+        .filter(|n| n.is_step() && n.section.sub_sections.is_empty())
+        .collect::<Vec<_>>();
+    let target_nodes = walk_sections(objects)
+        .filter(|n| n.depth() > 0 && target_section_name(n.section).is_some())
+        .collect::<Vec<_>>();
+    let build_start = nodes
+        .iter()
+        .chain(target_nodes.iter())
+        .map(|n| n.section.time_started_recording)
+        .min();
+    let Some(build_start) = build_start else {
+        return CriticalPathReport {
+            wall_time: 0.0,
+            step_time: 0.0,
+            average_parallelism: 0.0,
+            max_parallelism: 0,
+            critical_path: vec![],
+            critical_path_step_time: 0.0,
+            by_target: vec![],
+            target_spans: vec![],
+            timeline: vec![],
+            concurrency: vec![],
+        };
+    };
+    let steps = nodes
+        .iter()
+        .map(|node| {
+            let start = seconds_between(build_start, node.section.time_started_recording);
+            // Clock adjustments during the build can record a stop before the start
+            let end = seconds_between(build_start, node.section.time_stopped_recording);
+            Step {
+                target: node.target_name(),
+                title: &node.section.title,
+                start,
+                end: end.max(start),
+                metrics: node.section.task_metrics(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let wall_time = steps.iter().map(|s| s.end).fold(0.0, f64::max);
+    let step_time = steps.iter().map(|s| s.end - s.start).sum::<f64>();
+
+    let mut previous_end = 0.0;
+    let critical_path = critical_path(&steps)
+        .into_iter()
+        .map(|i| {
+            let step = &steps[i];
+            let wait = (step.start - previous_end).max(0.0);
+            previous_end = step.end;
+            CriticalStep {
+                target: step.target.map(str::to_string),
+                title: step.title.to_string(),
+                start: step.start,
+                duration: step.end - step.start,
+                wait,
+                cpu_time: step.metrics.as_ref().map(TaskMetrics::cpu_time),
+                max_rss: step.metrics.as_ref().map(|m| m.max_rss),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut per_target = BTreeMap::<&str, (usize, f64)>::new();
+    for step in critical_path.iter() {
+        let entry = per_target
+            .entry(step.target.as_deref().unwrap_or("<no target>"))
+            .or_default();
+        entry.0 += 1;
+        entry.1 += step.duration;
+    }
+    let mut by_target = per_target
+        .into_iter()
+        .map(|(target, (steps, duration))| TargetShare {
+            target: target.to_string(),
+            steps,
+            duration,
+            share: match wall_time > 0.0 {
+                true => duration / wall_time,
+                false => 0.0,
+            },
+        })
+        .collect::<Vec<_>>();
+    by_target.sort_by(|a, b| b.duration.total_cmp(&a.duration));
+
+    let mut target_spans = target_nodes
+        .iter()
+        .filter_map(|node| {
+            let target = target_section_name(node.section)?;
+            let start = seconds_between(build_start, node.section.time_started_recording);
+            let end = seconds_between(build_start, node.section.time_stopped_recording).max(start);
+            let step_time = steps
+                .iter()
+                .filter(|s| s.target == Some(target))
+                .map(|s| s.end - s.start)
+                .sum::<f64>();
+            Some(TargetSpan {
+                target: target.to_string(),
+                start,
+                end,
+                step_time,
+                parallelism: match end > start {
+                    true => step_time / (end - start),
+                    false => 0.0,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+    target_spans.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let concurrency = concurrency(&steps);
+    CriticalPathReport {
+        wall_time,
+        step_time,
+        average_parallelism: match wall_time > 0.0 {
+            true => step_time / wall_time,
+            false => 0.0,
+        },
+        max_parallelism: concurrency.iter().map(|c| c.steps).max().unwrap_or(0),
+        critical_path_step_time: critical_path.iter().map(|s| s.duration).sum(),
+        critical_path,
+        by_target,
+        target_spans,
+        timeline: timeline(&steps, wall_time),
+        concurrency,
+    }
+}
+
+impl fmt::Display for CriticalPathReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Wall time {:.2}s, step time {:.2}s, average parallelism {:.2}, max {}",
+            self.wall_time, self.step_time, self.average_parallelism, self.max_parallelism
+        )?;
+
+        writeln!(
+            f,
+            "\nCritical path ({} steps, {:.2}s in steps, {:.2}s waiting):",
+            self.critical_path.len(),
+            self.critical_path_step_time,
+            self.wall_time - self.critical_path_step_time
+        )?;
+        for step in self.critical_path.iter() {
+            writeln!(
+                f,
+                "  {:>8.2}s {:>8.2}s {:>7} {:>9} {:>8}  [{}] {}",
+                step.start,
+                step.duration,
+                match step.wait > 0.0 {
+                    true => format!("+{:.2}s", step.wait),
+                    false => String::new(),
+                },
+                step.cpu_time
+                    .map(|cpu| format!("cpu {cpu:.2}s"))
+                    .unwrap_or_default(),
+                step.max_rss.map(format_bytes).unwrap_or_default(),
+                step.target.as_deref().unwrap_or("<no target>"),
+                step.title
+            )?;
+        }
+
+        writeln!(f, "\nCritical path by target:")?;
+        for target in self.by_target.iter() {
+            writeln!(
+                f,
+                "  {:<32} {:>8.2}s {:>5.1}% {:>4} steps",
+                target.target,
+                target.duration,
+                target.share * 100.0,
+                target.steps
+            )?;
+        }
+
+        writeln!(f, "\nTargets:")?;
+        for span in self.target_spans.iter() {
+            writeln!(
+                f,
+                "  {:<32} {:>8.2}s - {:>8.2}s {:>8.2}s in steps, parallelism {:.2}",
+                span.target, span.start, span.end, span.step_time, span.parallelism
+            )?;
+        }
+
+        writeln!(f, "\nParallelism over time:")?;
+        for sample in self.timeline.iter() {
+            writeln!(
+                f,
+                "  {:>8.2}s {:>6.2} {}",
+                sample.start,
+                sample.parallelism,
+                "#".repeat((sample.parallelism * 4.0).round() as usize)
+            )?;
+        }
+
+        writeln!(f, "\nTime by number of running steps:")?;
+        for level in self.concurrency.iter() {
+            writeln!(f, "  {:>4} {:>8.2}s", level.steps, level.duration)?;
+        }
+        Ok(())
+    }
+}
+
+impl ToMarkdown for CriticalPathReport {
+    fn fmt_markdown(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Wall time **{:.2}s**, step time {:.2}s, average parallelism {:.2}, max {}\n",
+            self.wall_time, self.step_time, self.average_parallelism, self.max_parallelism
+        )?;
+
+        writeln!(
+            f,
+            "**Critical path** ({} steps, {:.2}s in steps, {:.2}s waiting)\n",
+            self.critical_path.len(),
+            self.critical_path_step_time,
+            self.wall_time - self.critical_path_step_time
+        )?;
+        write_table_header(
+            f,
+            &[
+                "Start", "Duration", "Wait", "CPU", "Peak RSS", "Target", "Step",
+            ],
+        )?;
+        for step in self.critical_path.iter() {
+            writeln!(
+                f,
+                "| {:.2}s | {:.2}s | {:.2}s | {} | {} | {} | {} |",
+                step.start,
+                step.duration,
+                step.wait,
+                step.cpu_time
+                    .map(|cpu| format!("{cpu:.2}s"))
+                    .unwrap_or_default(),
+                step.max_rss.map(format_bytes).unwrap_or_default(),
+                escape_cell(step.target.as_deref().unwrap_or("")),
+                escape_cell(&step.title)
+            )?;
+        }
+
+        writeln!(f, "\n**Critical path by target**\n")?;
+        write_table_header(f, &["Target", "Duration", "Share", "Steps"])?;
+        for target in self.by_target.iter() {
+            writeln!(
+                f,
+                "| {} | {:.2}s | {:.1}% | {} |",
+                escape_cell(&target.target),
+                target.duration,
+                target.share * 100.0,
+                target.steps
+            )?;
+        }
+
+        writeln!(f, "\n**Targets**\n")?;
+        write_table_header(f, &["Target", "Start", "End", "Step time", "Parallelism"])?;
+        for span in self.target_spans.iter() {
+            writeln!(
+                f,
+                "| {} | {:.2}s | {:.2}s | {:.2}s | {:.2} |",
+                escape_cell(&span.target),
+                span.start,
+                span.end,
+                span.step_time,
+                span.parallelism
+            )?;
+        }
+
+        writeln!(f, "\n**Time by number of running steps**\n")?;
+        write_table_header(f, &["Running steps", "Duration"])?;
+        for level in self.concurrency.iter() {
+            writeln!(f, "| {} | {:.2}s |", level.steps, level.duration)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_class::IDEActivityLogSection as Section;
+
+    fn step(title: &str, start: f64, end: f64) -> Step<'_> {
+        Step {
+            target: None,
+            title,
+            start,
+            end,
+            metrics: None,
+        }
+    }
+
+    fn levels(concurrency: &[ConcurrencyLevel]) -> Vec<(usize, f64)> {
+        concurrency.iter().map(|c| (c.steps, c.duration)).collect()
+    }
+
+    #[test]
+    fn follows_the_step_that_finished_last() {
+        // `c` waits for `b`, which started while `a` and `d` were running
+        let steps = [
+            step("a", 0.0, 2.0),
+            step("b", 1.0, 4.0),
+            step("c", 4.0, 6.0),
+            step("d", 0.5, 3.0),
+        ];
+        assert_eq!(critical_path(&steps), [1, 2]);
+        assert!(critical_path(&[]).is_empty());
+    }
+
+    #[test]
+    fn back_to_back_steps_do_not_overlap() {
+        let steps = [step("a", 0.0, 1.0), step("b", 1.0, 3.0)];
+        assert_eq!(critical_path(&steps), [0, 1]);
+        assert_eq!(levels(&concurrency(&steps)), [(1, 3.0)]);
+
+        let samples = timeline(&steps, 3.0);
+        assert_eq!(samples.len(), PARALLELISM_SAMPLES);
+        assert!(samples.iter().all(|s| (s.parallelism - 1.0).abs() < 1e-9));
+    }
+
+    #[test]
+    fn measures_overlapping_steps() {
+        let steps = [
+            step("a", 0.0, 2.0),
+            step("b", 1.0, 4.0),
+            step("c", 6.0, 8.0),
+        ];
+        assert_eq!(levels(&concurrency(&steps)), [(0, 2.0), (1, 5.0), (2, 1.0)]);
+
+        let samples = timeline(&steps, 8.0);
+        // Samples are 0.4s long, the third one is half in `a` alone and half in both
+        assert!((samples[2].parallelism - 1.5).abs() < 1e-9);
+        assert!((samples[3].parallelism - 2.0).abs() < 1e-9);
+        assert!(samples[12].parallelism.abs() < 1e-9);
+        assert!(timeline(&steps, 0.0).is_empty());
+    }
+
+    #[test]
+    fn clamps_steps_that_stop_before_they_start() {
+        let target = Section::test_target(
+            "App",
+            vec![
+                Section::test_step_in("App", "Compile a", 2),
+                Section::test_step_in("App", "Compile b", -2).test_shifted(3),
+            ],
+        );
+
+        let report = critical_path_report(&[Section::test_build(vec![target]).into()]);
+        assert_eq!(report.wall_time, 3.0);
+        assert_eq!(report.step_time, 2.0);
+        assert_eq!(report.max_parallelism, 1);
+        assert_eq!(levels(&report.concurrency), [(1, 2.0)]);
+        let path = report
+            .critical_path
+            .iter()
+            .map(|s| (s.title.as_str(), s.duration, s.wait))
+            .collect::<Vec<_>>();
+        assert_eq!(path, [("Compile a", 2.0, 0.0), ("Compile b", 0.0, 1.0)]);
+    }
+
+    #[test]
+    fn reports_target_spans() {
+        let app = Section::test_target(
+            "App",
+            vec![
+                Section::test_step_in("App", "Compile a", 2).test_shifted(2),
+                Section::test_step_in("App", "Link", 2).test_shifted(4),
+            ],
+        );
+        let kit = Section::test_target(
+            "Kit",
+            vec![
+                Section::test_step_in("Kit", "Compile k", 2),
+                Section::test_step_in("Kit", "Compile l", 2),
+            ],
+        );
+
+        let report = critical_path_report(&[Section::test_build(vec![app, kit]).into()]);
+        let spans = report
+            .target_spans
+            .iter()
+            .map(|s| {
+                (
+                    s.target.as_str(),
+                    s.start,
+                    s.end,
+                    s.step_time,
+                    s.parallelism,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [("Kit", 0.0, 2.0, 4.0, 2.0), ("App", 2.0, 6.0, 4.0, 1.0)]
+        );
+        assert_eq!(report.by_target[0].target, "App");
+    }
+}
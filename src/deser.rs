@@ -15,6 +15,17 @@ pub enum XActivityLogObject {
     IDEActivityLogCommandInvocationSection(IDEActivityLogCommandInvocationSection),
 }
 
+impl XActivityLogObject {
+    /// Sections at the top of the log tree held by this object
+    pub fn root_sections(&self) -> &[IDEActivityLogSection] {
+        match self {
+            XActivityLogObject::IDECommandLineBuildLog(log) => &log.sub_sections,
+            XActivityLogObject::IDEActivityLogSection(section) => std::slice::from_ref(section),
+            _ => &[],
+        }
+    }
+}
+
 pub struct Deserializer<'a, T>
 where
     T: Iterator<Item = Token>,
@@ -42,7 +53,7 @@ where
     type Item = XActivityLogObject;
 
     fn next(&mut self) -> Option<Self::Item> {
-        _deserialize(&mut self.tokens, &mut self.class_position_to_name)
+        _deserialize(self.tokens, &mut self.class_position_to_name)
     }
 }
 
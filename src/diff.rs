@@ -0,0 +1,454 @@
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::attachment::TaskMetrics;
+use crate::deser::XActivityLogObject;
+use crate::walk::{root_only_messages, walk_messages, walk_sections, SectionNode};
+
+/// Number of rows per table in the text form of the report
+const TEXT_REPORT_LIMIT: usize = 20;
+
+/// Duration changes below this many seconds are considered noise
+const MIN_DURATION_CHANGE: f64 = 0.01;
+
+/// Differences between two builds, durations are in seconds
+#[derive(Debug, Serialize)]
+pub struct BuildDiff {
+    pub base_duration: f64,
+    pub head_duration: f64,
+    pub duration_delta: f64,
+    pub added_steps: Vec<StepSummary>,
+    pub removed_steps: Vec<StepSummary>,
+    /// Steps present in both builds whose duration changed, biggest change first
+    pub changed_steps: Vec<StepChange>,
+    /// Steps present in both builds that were fetched from cache in only one of them
+    pub cache_changes: Vec<StepChange>,
+    pub new_warnings: Vec<DiagnosticSummary>,
+    pub fixed_warnings: Vec<DiagnosticSummary>,
+    pub new_errors: Vec<DiagnosticSummary>,
+    pub fixed_errors: Vec<DiagnosticSummary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepSummary {
+    pub target: Option<String>,
+    pub title: String,
+    pub signature: String,
+    pub duration: f64,
+    pub was_fetched_from_cache: bool,
+    /// User and system CPU time in seconds, from the task metrics of newer Xcode versions
+    pub cpu_time: Option<f64>,
+    /// Peak resident memory in bytes
+    pub max_rss: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepChange {
+    pub target: Option<String>,
+    pub title: String,
+    pub signature: String,
+    pub base_duration: f64,
+    pub head_duration: f64,
+    pub delta: f64,
+    pub base_was_fetched_from_cache: bool,
+    pub head_was_fetched_from_cache: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct DiagnosticSummary {
+    pub target: Option<String>,
+    pub title: String,
+}
+
+/// Steps are matched by target and signature (title when there is no signature).
+/// Repeated steps with the same key are matched in order of appearance.
+type StepKey = (Option<String>, String, usize);
+
+fn collect_steps(objects: &[XActivityLogObject]) -> Vec<(StepKey, StepSummary)> {
+    let mut occurrences = HashMap::<(Option<String>, String), usize>::new();
+    walk_sections(objects)
+        .filter(SectionNode::is_step)
+        .map(|node| {
+            let section = node.section;
+            let target = node.target_name().map(str::to_string);
+            let name = match section.signature.is_empty() {
+                true => section.title.clone(),
+                false => section.signature.clone(),
+            };
+            let occurrence = occurrences
+                .entry((target.clone(), name.clone()))
+                .or_default();
+            *occurrence += 1;
+            let metrics = section.task_metrics();
+            let summary = StepSummary {
+                target: target.clone(),
+                title: section.title.clone(),
+                signature: section.signature.clone(),
+                duration: section.duration().as_seconds_f64(),
+                was_fetched_from_cache: section.was_fetched_from_cache,
+                cpu_time: metrics.as_ref().map(TaskMetrics::cpu_time),
+                max_rss: metrics.map(|m| m.max_rss),
+            };
+            ((target, name, *occurrence), summary)
+        })
+        .collect()
+}
+
+/// Warnings and errors of the steps and the ones only the build itself reports
+fn collect_diagnostics(
+    objects: &[XActivityLogObject],
+) -> (Vec<DiagnosticSummary>, Vec<DiagnosticSummary>) {
+    let mut warnings = vec![];
+    let mut errors = vec![];
+    for (node, message) in walk_messages(objects).chain(root_only_messages(objects)) {
+        let summary = DiagnosticSummary {
+            target: node.target_name().map(str::to_string),
+            title: message.title.clone(),
+        };
+        if message.is_warning() {
+            warnings.push(summary);
+        } else if message.is_error() {
+            errors.push(summary);
+        }
+    }
+    (warnings, errors)
+}
+
+/// Items of `from` that are missing in `other`, counting duplicates
+fn subtract(from: &[DiagnosticSummary], other: &[DiagnosticSummary]) -> Vec<DiagnosticSummary> {
+    let mut remaining = HashMap::<&DiagnosticSummary, usize>::new();
+    for item in other {
+        *remaining.entry(item).or_default() += 1;
+    }
+    from.iter()
+        .filter(|item| match remaining.get_mut(item) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Longest step first, steps of the same duration by target and title
+fn by_duration(a: &StepSummary, b: &StepSummary) -> Ordering {
+    b.duration
+        .total_cmp(&a.duration)
+        .then_with(|| a.target.cmp(&b.target))
+        .then_with(|| a.title.cmp(&b.title))
+}
+
+fn build_duration(objects: &[XActivityLogObject]) -> f64 {
+    objects
+        .iter()
+        .flat_map(|o| o.root_sections())
+        .map(|s| s.duration().as_seconds_f64())
+        .sum()
+}
+
+/// Compares the `head` build against the `base` one
+pub fn diff(base: &[XActivityLogObject], head: &[XActivityLogObject]) -> BuildDiff {
+    let base_steps = collect_steps(base);
+    let mut head_steps = collect_steps(head).into_iter().collect::<HashMap<_, _>>();
+
+    let mut removed_steps = vec![];
+    let mut changed_steps = vec![];
+    let mut cache_changes = vec![];
+    for (key, base_step) in base_steps {
+        let Some(head_step) = head_steps.remove(&key) else {
+            removed_steps.push(base_step);
+            continue;
+        };
+        let change = StepChange {
+            target: base_step.target,
+            title: head_step.title,
+            signature: head_step.signature,
+            base_duration: base_step.duration,
+            head_duration: head_step.duration,
+            delta: head_step.duration - base_step.duration,
+            base_was_fetched_from_cache: base_step.was_fetched_from_cache,
+            head_was_fetched_from_cache: head_step.was_fetched_from_cache,
+        };
+        if change.base_was_fetched_from_cache != change.head_was_fetched_from_cache {
+            cache_changes.push(change.clone());
+        }
+        if change.delta.abs() >= MIN_DURATION_CHANGE {
+            changed_steps.push(change);
+        }
+    }
+    changed_steps.sort_by(|a, b| b.delta.abs().total_cmp(&a.delta.abs()));
+
+    let mut added_steps = head_steps.into_values().collect::<Vec<_>>();
+    added_steps.sort_by(by_duration);
+    removed_steps.sort_by(by_duration);
+
+    let (base_warnings, base_errors) = collect_diagnostics(base);
+    let (head_warnings, head_errors) = collect_diagnostics(head);
+
+    let base_duration = build_duration(base);
+    let head_duration = build_duration(head);
+    BuildDiff {
+        base_duration,
+        head_duration,
+        duration_delta: head_duration - base_duration,
+        added_steps,
+        removed_steps,
+        changed_steps,
+        cache_changes,
+        new_warnings: subtract(&head_warnings, &base_warnings),
+        fixed_warnings: subtract(&base_warnings, &head_warnings),
+        new_errors: subtract(&head_errors, &base_errors),
+        fixed_errors: subtract(&base_errors, &head_errors),
+    }
+}
+
+fn write_more(f: &mut fmt::Formatter<'_>, total: usize) -> fmt::Result {
+    if total > TEXT_REPORT_LIMIT {
+        writeln!(f, "  ... and {} more", total - TEXT_REPORT_LIMIT)?;
+    }
+    Ok(())
+}
+
+fn target_prefix(target: &Option<String>) -> String {
+    match target {
+        Some(target) => format!("[{target}] "),
+        None => String::new(),
+    }
+}
+
+impl fmt::Display for BuildDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Build duration: {:.2}s -> {:.2}s ({:+.2}s)",
+            self.base_duration, self.head_duration, self.duration_delta
+        )?;
+
+        for (name, steps) in [
+            ("Added steps", &self.added_steps),
+            ("Removed steps", &self.removed_steps),
+        ] {
+            writeln!(f, "\n{name} ({}):", steps.len())?;
+            for step in steps.iter().take(TEXT_REPORT_LIMIT) {
+                writeln!(
+                    f,
+                    "  {:>8.2}s  {}{}",
+                    step.duration,
+                    target_prefix(&step.target),
+                    step.title
+                )?;
+            }
+            write_more(f, steps.len())?;
+        }
+
+        writeln!(f, "\nDuration changes ({}):", self.changed_steps.len())?;
+        for step in self.changed_steps.iter().take(TEXT_REPORT_LIMIT) {
+            writeln!(
+                f,
+                "  {:>+8.2}s  {:.2}s -> {:.2}s  {}{}",
+                step.delta,
+                step.base_duration,
+                step.head_duration,
+                target_prefix(&step.target),
+                step.title
+            )?;
+        }
+        write_more(f, self.changed_steps.len())?;
+
+        writeln!(f, "\nCache changes ({}):", self.cache_changes.len())?;
+        for step in self.cache_changes.iter().take(TEXT_REPORT_LIMIT) {
+            let change = match step.head_was_fetched_from_cache {
+                true => "miss -> hit ",
+                false => "hit  -> miss",
+            };
+            writeln!(
+                f,
+                "  {change}  {}{}",
+                target_prefix(&step.target),
+                step.title
+            )?;
+        }
+        write_more(f, self.cache_changes.len())?;
+
+        for (name, diagnostics) in [
+            ("New errors", &self.new_errors),
+            ("Fixed errors", &self.fixed_errors),
+            ("New warnings", &self.new_warnings),
+            ("Fixed warnings", &self.fixed_warnings),
+        ] {
+            writeln!(f, "\n{name} ({}):", diagnostics.len())?;
+            for diagnostic in diagnostics.iter().take(TEXT_REPORT_LIMIT) {
+                writeln!(
+                    f,
+                    "  {}{}",
+                    target_prefix(&diagnostic.target),
+                    diagnostic.title
+                )?;
+            }
+            write_more(f, diagnostics.len())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_class::{IDEActivityLogMessage, IDEActivityLogSection as Section};
+    use crate::log_types::Severity;
+    use time::Duration;
+
+    fn diagnostic(title: &str) -> DiagnosticSummary {
+        DiagnosticSummary {
+            target: None,
+            title: title.to_string(),
+        }
+    }
+
+    fn titles(steps: &[StepSummary]) -> Vec<(Option<&str>, &str)> {
+        steps
+            .iter()
+            .map(|s| (s.target.as_deref(), s.title.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn matches_steps_by_target_and_signature() {
+        let base = [Section::test_build(vec![
+            Section::test_step_in("App", "Ld App", 2),
+            Section::test_step_in("Lib", "Ld App", 1),
+            Section::test_step_in("App", "PhaseScriptExecution Lint", 1),
+            Section::test_step_in("App", "PhaseScriptExecution Lint", 1),
+        ])
+        .into()];
+        let head = [Section::test_build(vec![
+            Section::test_step_in("App", "Ld App", 5),
+            Section::test_step_in("Kit", "Ld App", 1),
+            Section::test_step_in("App", "PhaseScriptExecution Lint", 1),
+            Section::test_step_in("App", "PhaseScriptExecution Lint", 3),
+            Section::test_step_in("App", "PhaseScriptExecution Lint", 1),
+        ])
+        .into()];
+        let diff = diff(&base, &head);
+        assert_eq!(
+            titles(&diff.added_steps),
+            [
+                (Some("App"), "PhaseScriptExecution Lint"),
+                (Some("Kit"), "Ld App")
+            ]
+        );
+        assert_eq!(titles(&diff.removed_steps), [(Some("Lib"), "Ld App")]);
+        let changes = diff
+            .changed_steps
+            .iter()
+            .map(|c| (c.title.as_str(), c.delta))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            [("Ld App", 3.0), ("PhaseScriptExecution Lint", 2.0)]
+        );
+    }
+
+    #[test]
+    fn orders_steps_of_the_same_duration() {
+        let head = [Section::test_build(
+            (0..10)
+                .map(|i| Section::test_step_in(&format!("T{}", 9 - i), "Touch /Build/App", 1))
+                .chain([Section::test_step_in("A", "Ld App", 2)])
+                .collect(),
+        )
+        .into()];
+        let diff = diff(&[], &head);
+        let targets = diff
+            .added_steps
+            .iter()
+            .map(|s| s.target.as_deref().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            ["A", "T0", "T1", "T2", "T3", "T4", "T5", "T6", "T7", "T8", "T9"]
+        );
+    }
+
+    #[test]
+    fn ignores_small_duration_changes() {
+        let base = [Section::test_build(vec![Section::test_step_in("App", "Ld App", 1)]).into()];
+        let slower_by = |milliseconds| {
+            let mut ld = Section::test_step_in("App", "Ld App", 1);
+            ld.time_stopped_recording += Duration::milliseconds(milliseconds);
+            [Section::test_build(vec![ld]).into()]
+        };
+        assert!(diff(&base, &slower_by(5)).changed_steps.is_empty());
+
+        let changed = diff(&base, &slower_by(10)).changed_steps;
+        assert_eq!(changed.len(), 1);
+        assert!(changed[0].delta >= MIN_DURATION_CHANGE);
+    }
+
+    #[test]
+    fn reports_cache_changes() {
+        let mut cached = Section::test_step_in("App", "SwiftCompile normal arm64 /src/A.swift", 1);
+        cached.was_fetched_from_cache = true;
+        let base = [Section::test_build(vec![
+            cached,
+            Section::test_step_in("App", "SwiftCompile normal arm64 /src/B.swift", 1),
+        ])
+        .into()];
+        let head = [Section::test_build(vec![
+            Section::test_step_in("App", "SwiftCompile normal arm64 /src/A.swift", 1),
+            Section::test_step_in("App", "SwiftCompile normal arm64 /src/B.swift", 1),
+        ])
+        .into()];
+        let diff = diff(&base, &head);
+        assert!(diff.changed_steps.is_empty());
+        assert_eq!(diff.cache_changes.len(), 1);
+        let change = &diff.cache_changes[0];
+        assert_eq!(change.title, "SwiftCompile normal arm64 /src/A.swift");
+        assert!(change.base_was_fetched_from_cache);
+        assert!(!change.head_was_fetched_from_cache);
+    }
+
+    #[test]
+    fn subtracts_duplicate_diagnostics() {
+        let from = [
+            diagnostic("unused variable 'x'"),
+            diagnostic("unused variable 'x'"),
+            diagnostic("unused variable 'x'"),
+            diagnostic("deprecated"),
+        ];
+        let other = [diagnostic("unused variable 'x'"), diagnostic("unrelated")];
+        assert_eq!(
+            subtract(&from, &other),
+            [
+                diagnostic("unused variable 'x'"),
+                diagnostic("unused variable 'x'"),
+                diagnostic("deprecated"),
+            ]
+        );
+        assert!(subtract(&other[..1], &from).is_empty());
+    }
+
+    #[test]
+    fn compares_errors_only_reported_by_the_build() {
+        let build_failing_with = |title| {
+            let mut build = Section::test_build(vec![]);
+            build.messages = vec![IDEActivityLogMessage::test_message(title, Severity::Error)];
+            [build.into()]
+        };
+        let base = build_failing_with("No profiles for 'com.example.App'");
+        let head = build_failing_with("Scheme App is not currently configured");
+        let diff = diff(&base, &head);
+        assert_eq!(
+            diff.new_errors,
+            [diagnostic("Scheme App is not currently configured")]
+        );
+        assert_eq!(
+            diff.fixed_errors,
+            [diagnostic("No profiles for 'com.example.App'")]
+        );
+    }
+}
@@ -0,0 +1,419 @@
+use std::iter::Peekable;
+
+use anyhow::{anyhow, bail};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::log_class::*;
+use crate::log_types::{DomainType, SectionType};
+use crate::token::Token;
+
+/// Pull-based alternative to [`crate::deser::Deserializer`].
+/// Instead of materialising the whole section tree it yields events as soon
+/// as the corresponding tokens are read, so only the chain of currently open
+/// sections is kept in memory.
+#[derive(Debug, Serialize)]
+pub enum LogEvent {
+    SectionStart(SectionStart),
+    Message(IDEActivityLogMessage),
+    Attachment(IDEActivityLogSectionAttachment),
+    SectionEnd(SectionEnd),
+}
+
+/// Fields of a section that precede its children in the SLF stream
+#[derive(Debug, Serialize)]
+pub struct SectionStart {
+    pub depth: usize,
+    pub section_type: SectionType,
+    pub domain_type: DomainType,
+    pub title: String,
+    pub signature: String,
+    #[serde(with = "time::serde::iso8601")]
+    pub time_started_recording: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    pub time_stopped_recording: OffsetDateTime,
+}
+
+/// Fields of a section that are only known once its children were read
+#[derive(Debug, Default, Serialize)]
+pub struct SectionEnd {
+    pub depth: usize,
+    pub text: Option<String>,
+    pub was_cancelled: bool,
+    pub is_quiet: bool,
+    pub was_fetched_from_cache: bool,
+    pub subtitle: Option<String>,
+    pub location: Option<DVTDocumentLocation>,
+    pub command_details_spect: Option<String>,
+    pub unique_identifier: Option<String>,
+    pub localized_result_string: Option<String>,
+    pub xcbuild_signature: Option<String>,
+    pub unknown: Option<u64>,
+}
+
+/// Which class an open section was read as, it defines the fields after `sub_sections`
+enum SectionClass {
+    CommandLineBuildLog,
+    CommandInvocation,
+    Section,
+}
+
+enum Stage {
+    SubSections,
+    Messages,
+    Attachments { attachments_found: bool },
+}
+
+struct OpenSection {
+    class: SectionClass,
+    stage: Stage,
+    remaining: usize,
+    end: SectionEnd,
+}
+
+pub struct EventReader<'a, T>
+where
+    T: Iterator<Item = Token>,
+{
+    tokens: &'a mut Peekable<T>,
+    class_position_to_name: Vec<String>,
+    open_sections: Vec<OpenSection>,
+    failed: bool,
+}
+
+impl<'a, T> EventReader<'a, T>
+where
+    T: Iterator<Item = Token>,
+{
+    pub fn new(tokens: &'a mut Peekable<T>) -> Self {
+        Self {
+            tokens,
+            class_position_to_name: vec![],
+            open_sections: vec![],
+            failed: false,
+        }
+    }
+
+    fn next_token(&mut self) -> anyhow::Result<Token> {
+        self.tokens.next().ok_or_else(|| anyhow!("No more tokens"))
+    }
+
+    /// Registers class names until a class instance is found and returns its class name.
+    /// `None` means that the element is absent (`Null` or `Json` placeholder was consumed).
+    fn peek_class_instance(&mut self) -> anyhow::Result<Option<String>> {
+        loop {
+            match self.tokens.peek() {
+                Some(Token::ClassName(_)) => {
+                    let name = self.next_token()?.to_string();
+                    self.class_position_to_name.push(name);
+                }
+                Some(Token::ClassInstance(position)) => {
+                    let name = position
+                        .checked_sub(1)
+                        .and_then(|i| self.class_position_to_name.get(i));
+                    return match name {
+                        Some(name) => Ok(Some(name.clone())),
+                        None => bail!("Unknown class position {position}"),
+                    };
+                }
+                Some(Token::Null) | Some(Token::Json(_)) => {
+                    self.tokens.next();
+                    return Ok(None);
+                }
+                Some(v) => bail!("Unexpected token: {v:?}"),
+                None => bail!("No more tokens"),
+            }
+        }
+    }
+
+    fn open_section(&mut self, class: SectionClass) -> anyhow::Result<LogEvent> {
+        let _class_instance = usize::try_from(self.next_token()?)?;
+        let start = SectionStart {
+            depth: self.open_sections.len(),
+            section_type: SectionType::from(i8::try_from(self.next_token()?)?),
+            domain_type: DomainType::from(String::try_from(self.next_token()?)?),
+            title: String::try_from(self.next_token()?)?,
+            signature: String::try_from(self.next_token()?)?,
+            time_started_recording: OffsetDateTime::try_from(self.next_token()?)?,
+            time_stopped_recording: OffsetDateTime::try_from(self.next_token()?)?,
+        };
+        let sub_sections_size = Option::<usize>::try_from(self.next_token()?)?.unwrap_or(0);
+        log::debug!("Open section {:?}", start.title);
+
+        self.open_sections.push(OpenSection {
+            class,
+            stage: Stage::SubSections,
+            remaining: sub_sections_size,
+            end: SectionEnd {
+                depth: start.depth,
+                ..Default::default()
+            },
+        });
+        Ok(LogEvent::SectionStart(start))
+    }
+
+    /// Reads an object that is not nested in any section
+    fn read_top_level(&mut self) -> anyhow::Result<Option<LogEvent>> {
+        let class_name = loop {
+            match self.tokens.peek() {
+                None => return Ok(None),
+                Some(Token::ClassName(_)) | Some(Token::ClassInstance(_)) => {
+                    match self.peek_class_instance()? {
+                        Some(class_name) => break class_name,
+                        None => continue,
+                    }
+                }
+                Some(v) => {
+                    log::warn!("Unknwon value: {v:?}");
+                    self.tokens.next();
+                }
+            }
+        };
+
+        let event = match class_name.as_str() {
+            "IDECommandLineBuildLog" => self.open_section(SectionClass::CommandLineBuildLog)?,
+            "IDEActivityLogCommandInvocationSection" => {
+                self.open_section(SectionClass::CommandInvocation)?
+            }
+            "IDEActivityLogSection" => self.open_section(SectionClass::Section)?,
+            "IDEActivityLogMessage" | "IDEDiagnosticActivityLogMessage" => LogEvent::Message(
+                IDEActivityLogMessage::from_tokens(self.tokens, &mut self.class_position_to_name)?,
+            ),
+            "IDEActivityLogSectionAttachment" => {
+                LogEvent::Attachment(IDEActivityLogSectionAttachment::from_tokens(
+                    self.tokens,
+                    &mut self.class_position_to_name,
+                )?)
+            }
+            s => bail!("Unexpected top level class instance: {s:?}"),
+        };
+        Ok(Some(event))
+    }
+
+    /// Reads the fields between `sub_sections` and `messages`
+    fn read_text_and_messages_size(&mut self) -> anyhow::Result<usize> {
+        let text = Option::<String>::try_from(self.next_token()?)?;
+        let messages_size = Option::<usize>::try_from(self.next_token()?)?.unwrap_or(0);
+        let section = self.open_sections.last_mut().unwrap();
+        section.end.text = text;
+        Ok(messages_size)
+    }
+
+    /// Reads the fields between `messages` and `attachments`
+    fn read_section_tail(&mut self) -> anyhow::Result<(bool, usize)> {
+        let was_cancelled = bool::try_from(self.next_token()?)?;
+        let is_quiet = bool::try_from(self.next_token()?)?;
+        let was_fetched_from_cache = bool::try_from(self.next_token()?)?;
+        let subtitle = Option::<String>::try_from(self.next_token()?)?;
+        let location = match self.peek_class_instance()? {
+            Some(_) => Some(DVTDocumentLocation::from_tokens(
+                self.tokens,
+                &mut self.class_position_to_name,
+            )?),
+            None => None,
+        };
+        let command_details_spect = Option::<String>::try_from(self.next_token()?)?;
+        let unique_identifier = Option::<String>::try_from(self.next_token()?)?;
+        let localized_result_string = Option::<String>::try_from(self.next_token()?)?;
+        let xcbuild_signature = Option::<String>::try_from(self.next_token()?)?;
+        let (attachments_found, attachments_size) = match self.tokens.peek() {
+            Some(Token::Array(_)) => (
+                true,
+                Option::<usize>::try_from(self.next_token()?)?.unwrap_or(0),
+            ),
+            _ => (false, 0),
+        };
+
+        let end = &mut self.open_sections.last_mut().unwrap().end;
+        end.was_cancelled = was_cancelled;
+        end.is_quiet = is_quiet;
+        end.was_fetched_from_cache = was_fetched_from_cache;
+        end.subtitle = subtitle;
+        end.location = location;
+        end.command_details_spect = command_details_spect;
+        end.unique_identifier = unique_identifier;
+        end.localized_result_string = localized_result_string;
+        end.xcbuild_signature = xcbuild_signature;
+        Ok((attachments_found, attachments_size))
+    }
+
+    fn close_section(&mut self) -> anyhow::Result<LogEvent> {
+        let mut section = self.open_sections.pop().unwrap();
+        if let Stage::Attachments {
+            attachments_found: true,
+        } = section.stage
+        {
+            if let Some(Token::Int(_)) = self.tokens.peek() {
+                section.end.unknown = Option::<u64>::try_from(self.next_token()?)?;
+            }
+        }
+        Ok(LogEvent::SectionEnd(section.end))
+    }
+
+    fn read_event(&mut self) -> anyhow::Result<Option<LogEvent>> {
+        loop {
+            let Some(section) = self.open_sections.last_mut() else {
+                return self.read_top_level();
+            };
+
+            if section.remaining > 0 {
+                section.remaining -= 1;
+                let Some(class_name) = self.peek_class_instance()? else {
+                    // Mirrors `deser_vec`: a missing element ends the collection
+                    self.open_sections.last_mut().unwrap().remaining = 0;
+                    continue;
+                };
+                let section = self.open_sections.last().unwrap();
+                let event = match section.stage {
+                    Stage::SubSections => self.open_section(SectionClass::Section)?,
+                    Stage::Messages => {
+                        let mut message = IDEActivityLogMessage::from_tokens(
+                            self.tokens,
+                            &mut self.class_position_to_name,
+                        )
+                        .map_err(|e| anyhow!("Failed to parse {class_name} with {e:?}"))?;
+                        message.resolve_section_text(section.end.text.as_deref());
+                        LogEvent::Message(message)
+                    }
+                    Stage::Attachments { .. } => LogEvent::Attachment(
+                        IDEActivityLogSectionAttachment::from_tokens(
+                            self.tokens,
+                            &mut self.class_position_to_name,
+                        )
+                        .map_err(|e| anyhow!("Failed to parse {class_name} with {e:?}"))?,
+                    ),
+                };
+                return Ok(Some(event));
+            }
+
+            match (&section.class, &section.stage) {
+                (SectionClass::CommandLineBuildLog, Stage::SubSections) => {
+                    return self.close_section().map(Some);
+                }
+                (_, Stage::SubSections) => {
+                    let messages_size = self.read_text_and_messages_size()?;
+                    let section = self.open_sections.last_mut().unwrap();
+                    section.stage = Stage::Messages;
+                    section.remaining = messages_size;
+                }
+                (SectionClass::CommandInvocation, Stage::Messages) => {
+                    let was_cancelled = bool::try_from(self.next_token()?)?;
+                    self.open_sections.last_mut().unwrap().end.was_cancelled = was_cancelled;
+                    return self.close_section().map(Some);
+                }
+                (_, Stage::Messages) => {
+                    let (attachments_found, attachments_size) = self.read_section_tail()?;
+                    let section = self.open_sections.last_mut().unwrap();
+                    section.stage = Stage::Attachments { attachments_found };
+                    section.remaining = attachments_size;
+                }
+                (_, Stage::Attachments { .. }) => {
+                    return self.close_section().map(Some);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T> Iterator for EventReader<'a, T>
+where
+    T: Iterator<Item = Token>,
+{
+    type Item = anyhow::Result<LogEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.read_event() {
+            Ok(event) => event.map(Ok),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deser::{Deserializer, XActivityLogObject};
+    use crate::parser::Parser;
+    use crate::slf_writer::SlfWriter;
+
+    fn tokens(contents: &[u8]) -> Peekable<impl Iterator<Item = Token>> {
+        Parser::new(contents)
+            .iter()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .peekable()
+    }
+
+    /// Same order as the events: section start, sub sections, messages, section end
+    fn flatten(section: &IDEActivityLogSection, depth: usize, out: &mut Vec<String>) {
+        out.push(format!("start {depth} {}", section.title));
+        for sub_section in &section.sub_sections {
+            flatten(sub_section, depth + 1, out);
+        }
+        for message in &section.messages {
+            out.push(format!("message {:?} {}", message.severity, message.title));
+        }
+        out.push(format!(
+            "end {depth} {:?} {}",
+            section.text, section.was_fetched_from_cache
+        ));
+    }
+
+    #[test]
+    fn reads_the_same_log_as_the_deserializer() {
+        let mut slf = SlfWriter::default();
+        slf.section_start(2, "Build App", 0.0, 1.0, 2);
+        slf.section_start(2, "Compile A.swift", 1.0, 2.0, 0);
+        slf.section_end(
+            Some("swift-frontend A.swift"),
+            &[("unused variable", 1), ("cannot find 'x'", 2)],
+            false,
+            None,
+        );
+        slf.section_start(2, "Build target Kit", 2.0, 3.0, 1);
+        slf.section_start(2, "Compile K.swift", 3.0, 4.0, 0);
+        slf.section_end(None, &[], true, None);
+        slf.section_end(None, &[], false, None);
+        slf.section_end(None, &[("cannot find 'x'", 2)], false, None);
+
+        let mut deser_tokens = tokens(&slf.contents);
+        let mut expected = vec![];
+        for object in Deserializer::new(&mut deser_tokens) {
+            let XActivityLogObject::IDEActivityLogSection(section) = object else {
+                panic!("Expected only sections");
+            };
+            flatten(&section, 0, &mut expected);
+        }
+
+        let mut event_tokens = tokens(&slf.contents);
+        let events = EventReader::new(&mut event_tokens)
+            .map(|event| match event.unwrap() {
+                LogEvent::SectionStart(start) => format!("start {} {}", start.depth, start.title),
+                LogEvent::Message(message) => {
+                    format!("message {:?} {}", message.severity, message.title)
+                }
+                LogEvent::SectionEnd(end) => format!(
+                    "end {} {:?} {}",
+                    end.depth, end.text, end.was_fetched_from_cache
+                ),
+                LogEvent::Attachment(attachment) => format!("attachment {}", attachment.identifier),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 11);
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn fails_on_class_instance_zero() {
+        let mut tokens = vec![Token::ClassInstance(0)].into_iter().peekable();
+        let mut reader = EventReader::new(&mut tokens);
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
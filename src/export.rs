@@ -1,9 +1,16 @@
 use serde::Serialize;
 
+use crate::deser::XActivityLogObject;
+use crate::log_class::{
+    DVTDocumentLocation, IDEActivityLogMessage, IDEActivityLogSection,
+    IDEActivityLogSectionAttachment,
+};
+use crate::log_types::{DomainType, SectionType, Severity};
 use crate::token::Token;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::PathBuf;
+use time::OffsetDateTime;
 
 pub fn to_csv(tokens: impl IntoIterator<Item = Token>, path: &PathBuf) -> anyhow::Result<()> {
     let file = File::create(path)?;
@@ -11,7 +18,7 @@ pub fn to_csv(tokens: impl IntoIterator<Item = Token>, path: &PathBuf) -> anyhow
     writeln!(file, "type,value")?;
     let mut counter = 0;
     for token in tokens.into_iter() {
-        writeln!(file, "{},{}", token.get_type_as_str(), token.to_string())?;
+        writeln!(file, "{},{}", token.get_type_as_str(), token)?;
         counter += 1;
 
         if counter % 1000 == 0 {
@@ -28,23 +35,331 @@ pub fn to_json<S: Serialize>(
     let file = File::create(path)?;
     let mut file = BufWriter::new(file);
 
-    writeln!(file, "{}", "[")?;
+    writeln!(file, "[")?;
 
     let mut logs = tokens.into_iter().peekable();
-    loop {
-        match logs.peek() {
-            Some(_) => {
-                let json_str = serde_json::to_string_pretty(&logs.next().unwrap())?;
-                if logs.peek().is_some() {
-                    writeln!(file, "{},", json_str)?;
-                } else {
-                    writeln!(file, "{}", json_str)?;
+    while let Some(log) = logs.next() {
+        let json_str = serde_json::to_string_pretty(&log)?;
+        if logs.peek().is_some() {
+            writeln!(file, "{},", json_str)?;
+        } else {
+            writeln!(file, "{}", json_str)?;
+        }
+    }
+
+    writeln!(file, "]")?;
+    Ok(())
+}
+
+/// Writes one JSON record per line: every section and every message of the
+/// log tree becomes its own flat record that references its parent by `parent_id`
+/// and carries the titles of its ancestors in `path`.
+pub fn to_ndjson(
+    objects: impl IntoIterator<Item = XActivityLogObject>,
+    path: &PathBuf,
+) -> anyhow::Result<()> {
+    let file = File::create(path)?;
+    let mut writer = NdjsonWriter {
+        out: BufWriter::new(file),
+        next_id: 0,
+    };
+
+    for object in objects.into_iter() {
+        writer.write_object(&object)?;
+    }
+
+    writer.out.flush()?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum NdjsonRecord<'a> {
+    Log {
+        id: usize,
+        section_type: SectionType,
+        domain_type: &'a DomainType,
+        title: &'a str,
+        signature: &'a str,
+        #[serde(with = "time::serde::iso8601")]
+        time_started_recording: OffsetDateTime,
+        #[serde(with = "time::serde::iso8601")]
+        time_stopped_recording: OffsetDateTime,
+    },
+    Section {
+        id: usize,
+        parent_id: Option<usize>,
+        path: &'a [&'a str],
+        section_type: SectionType,
+        domain_type: &'a DomainType,
+        title: &'a str,
+        signature: &'a str,
+        #[serde(with = "time::serde::iso8601")]
+        time_started_recording: OffsetDateTime,
+        #[serde(with = "time::serde::iso8601")]
+        time_stopped_recording: OffsetDateTime,
+        text: Option<&'a str>,
+        was_cancelled: bool,
+        is_quiet: bool,
+        was_fetched_from_cache: bool,
+        subtitle: Option<&'a str>,
+        location: Option<&'a DVTDocumentLocation>,
+        command_details_spect: Option<&'a str>,
+        unique_identifier: Option<&'a str>,
+        localized_result_string: Option<&'a str>,
+        xcbuild_signature: Option<&'a str>,
+        attachments: &'a [IDEActivityLogSectionAttachment],
+        unknown: Option<u64>,
+    },
+    Message {
+        id: usize,
+        parent_id: Option<usize>,
+        path: &'a [&'a str],
+        title: &'a str,
+        short_title: Option<&'a str>,
+        #[serde(with = "time::serde::iso8601")]
+        time_emitted: OffsetDateTime,
+        range_end_in_section_text: u64,
+        range_start_in_section_text: u64,
+        severity: Severity,
+        r#type: Option<&'a str>,
+        location: Option<&'a DVTDocumentLocation>,
+        category_ident: Option<&'a str>,
+        secondary_locations: &'a [DVTDocumentLocation],
+        additional_description: Option<&'a str>,
+        section_text: Option<&'a str>,
+    },
+    Object {
+        id: usize,
+        value: &'a XActivityLogObject,
+    },
+}
+
+struct NdjsonWriter<W: Write> {
+    out: W,
+    next_id: usize,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    fn take_id(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn write_record(&mut self, record: &NdjsonRecord) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.out, record)?;
+        writeln!(self.out)?;
+        if self.next_id.is_multiple_of(1000) {
+            log::debug!("Written {} records", self.next_id);
+        }
+        Ok(())
+    }
+
+    fn write_object(&mut self, object: &XActivityLogObject) -> anyhow::Result<()> {
+        match object {
+            XActivityLogObject::IDECommandLineBuildLog(log) => {
+                let id = self.take_id();
+                self.write_record(&NdjsonRecord::Log {
+                    id,
+                    section_type: log.section_type,
+                    domain_type: &log.domain_type,
+                    title: &log.title,
+                    signature: &log.signature,
+                    time_started_recording: log.time_started_recording,
+                    time_stopped_recording: log.time_stopped_recording,
+                })?;
+                let mut path = vec![log.title.as_str()];
+                for section in log.sub_sections.iter() {
+                    self.write_section(section, Some(id), &mut path)?;
                 }
+                Ok(())
+            }
+            XActivityLogObject::IDEActivityLogSection(section) => {
+                self.write_section(section, None, &mut vec![])
+            }
+            XActivityLogObject::IDEActivityLogMessage(message) => {
+                self.write_message(message, None, &mut vec![])
+            }
+            other => {
+                let id = self.take_id();
+                self.write_record(&NdjsonRecord::Object { id, value: other })
             }
-            None => break,
         }
     }
 
-    writeln!(file, "{}", "]")?;
-    Ok(())
+    fn write_section<'a>(
+        &mut self,
+        section: &'a IDEActivityLogSection,
+        parent_id: Option<usize>,
+        path: &mut Vec<&'a str>,
+    ) -> anyhow::Result<()> {
+        let id = self.take_id();
+        self.write_record(&NdjsonRecord::Section {
+            id,
+            parent_id,
+            path: path.as_slice(),
+            section_type: section.section_type,
+            domain_type: &section.domain_type,
+            title: &section.title,
+            signature: &section.signature,
+            time_started_recording: section.time_started_recording,
+            time_stopped_recording: section.time_stopped_recording,
+            text: section.text.as_deref(),
+            was_cancelled: section.was_cancelled,
+            is_quiet: section.is_quiet,
+            was_fetched_from_cache: section.was_fetched_from_cache,
+            subtitle: section.subtitle.as_deref(),
+            location: section.location.as_ref(),
+            command_details_spect: section.command_details_spect.as_deref(),
+            unique_identifier: section.unique_identifier.as_deref(),
+            localized_result_string: section.localized_result_string.as_deref(),
+            xcbuild_signature: section.xcbuild_signature.as_deref(),
+            attachments: &section.attachments,
+            unknown: section.unknown,
+        })?;
+
+        path.push(&section.title);
+        for message in section.messages.iter() {
+            self.write_message(message, Some(id), path)?;
+        }
+        for sub_section in section.sub_sections.iter() {
+            self.write_section(sub_section, Some(id), path)?;
+        }
+        path.pop();
+        Ok(())
+    }
+
+    fn write_message<'a>(
+        &mut self,
+        message: &'a IDEActivityLogMessage,
+        parent_id: Option<usize>,
+        path: &mut Vec<&'a str>,
+    ) -> anyhow::Result<()> {
+        let id = self.take_id();
+        self.write_record(&NdjsonRecord::Message {
+            id,
+            parent_id,
+            path: path.as_slice(),
+            title: &message.title,
+            short_title: message.short_title.as_deref(),
+            time_emitted: message.time_emitted,
+            range_end_in_section_text: message.range_end_in_section_text,
+            range_start_in_section_text: message.range_start_in_section_text,
+            severity: message.severity,
+            r#type: message.r#type.as_deref(),
+            location: message.location.as_ref(),
+            category_ident: message.category_ident.as_deref(),
+            secondary_locations: &message.secondary_locations,
+            additional_description: message.additional_description.as_deref(),
+            section_text: message.section_text.as_deref(),
+        })?;
+
+        path.push(&message.title);
+        for sub_message in message.sub_messages.iter() {
+            self.write_message(sub_message, Some(id), path)?;
+        }
+        path.pop();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn to_records(objects: &[XActivityLogObject]) -> (String, Vec<Value>) {
+        let mut writer = NdjsonWriter {
+            out: Vec::new(),
+            next_id: 0,
+        };
+        for object in objects {
+            writer.write_object(object).unwrap();
+        }
+        let output = String::from_utf8(writer.out).unwrap();
+        let records = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        (output, records)
+    }
+
+    fn nested_build() -> XActivityLogObject {
+        let mut error = IDEActivityLogMessage::test_message("expected ';'", Severity::Error);
+        error.sub_messages = vec![IDEActivityLogMessage::test_message(
+            "insert ';'",
+            Severity::Note,
+        )];
+        let mut step = IDEActivityLogSection::test_step("Compile util.c", "CompileC util.c", None);
+        step.messages = vec![error];
+        let mut target =
+            IDEActivityLogSection::test_step("Build target App of project App", "", None);
+        target.sub_sections = vec![step];
+        let mut build = IDEActivityLogSection::test_step("Build App", "", None);
+        build.sub_sections = vec![
+            target,
+            IDEActivityLogSection::test_step("Touch App", "Touch /Build/App", None),
+        ];
+        XActivityLogObject::IDEActivityLogSection(build)
+    }
+
+    #[test]
+    fn writes_one_object_per_line() {
+        let (output, records) = to_records(&[nested_build(), nested_build()]);
+        assert!(output.ends_with('\n'));
+        assert_eq!(output.lines().count(), 12);
+        assert_eq!(records.len(), 12);
+        assert!(records.iter().all(Value::is_object));
+    }
+
+    #[test]
+    fn flattens_nested_sections_and_messages() {
+        let (_, records) = to_records(&[nested_build()]);
+        let summary = records
+            .iter()
+            .map(|r| {
+                (
+                    r["record"].as_str().unwrap(),
+                    r["id"].as_u64().unwrap(),
+                    r["parent_id"].as_u64(),
+                    r["title"].as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("section", 0, None, "Build App"),
+                ("section", 1, Some(0), "Build target App of project App"),
+                ("section", 2, Some(1), "Compile util.c"),
+                ("message", 3, Some(2), "expected ';'"),
+                ("message", 4, Some(3), "insert ';'"),
+                ("section", 5, Some(0), "Touch App"),
+            ]
+        );
+        assert_eq!(records[0]["path"], serde_json::json!([]));
+        assert_eq!(
+            records[4]["path"],
+            serde_json::json!([
+                "Build App",
+                "Build target App of project App",
+                "Compile util.c",
+                "expected ';'"
+            ])
+        );
+        assert_eq!(records[5]["path"], serde_json::json!(["Build App"]));
+    }
+
+    #[test]
+    fn numbers_records_across_objects() {
+        let (_, records) = to_records(&[nested_build(), nested_build()]);
+        let ids = records
+            .iter()
+            .map(|r| r["id"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, (0..12).collect::<Vec<_>>());
+        assert_eq!(records[6]["parent_id"], Value::Null);
+        assert_eq!(records[7]["parent_id"], 6);
+    }
 }
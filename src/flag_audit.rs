@@ -0,0 +1,525 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::command::BuildCommand;
+use crate::deser::XActivityLogObject;
+use crate::walk::{walk_sections, SectionNode};
+
+/// Compiler flags of the compile steps per target, flags that differ between
+/// targets of the same configuration and risky settings of release builds
+#[derive(Debug, Serialize)]
+pub struct FlagAuditReport {
+    pub targets: Vec<TargetFlags>,
+    pub inconsistencies: Vec<FlagInconsistency>,
+    pub release_warnings: Vec<FlagWarning>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    Swift,
+    Clang,
+}
+
+/// Settings that matter for the build configuration, other flags are ignored
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct CompilerFlags {
+    /// Last optimization flag, e.g. `-Onone` or `-Os`
+    pub optimization: Option<String>,
+    pub enable_testing: bool,
+    pub whole_module: bool,
+    pub sanitizers: Vec<String>,
+    /// Last debug info flag, e.g. `-g` or `-gline-tables-only`
+    pub debug_info: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TargetFlags {
+    pub target: String,
+    pub configuration: Option<String>,
+    pub language: Language,
+    /// Flags used by most compile steps of the target
+    pub flags: CompilerFlags,
+    pub steps: usize,
+    /// Other flag combinations used by some of the steps
+    pub variants: Vec<FlagVariant>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FlagVariant {
+    #[serde(flatten)]
+    pub flags: CompilerFlags,
+    pub steps: usize,
+}
+
+/// Flag set differently by the targets of one configuration
+#[derive(Debug, Serialize)]
+pub struct FlagInconsistency {
+    pub configuration: Option<String>,
+    pub language: Language,
+    pub flag: &'static str,
+    pub values: Vec<FlagValue>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FlagValue {
+    pub value: String,
+    pub targets: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FlagWarning {
+    pub target: String,
+    pub configuration: Option<String>,
+    pub language: Language,
+    pub message: String,
+}
+
+fn step_language(node: &SectionNode) -> Option<Language> {
+    let kind = node.section.step_info().kind;
+    if kind.is_clang_compile() {
+        Some(Language::Clang)
+    } else if kind.is_swift_compile() {
+        Some(Language::Swift)
+    } else {
+        None
+    }
+}
+
+fn is_optimization_flag(language: Language, arg: &str) -> bool {
+    match language {
+        Language::Swift => matches!(arg, "-Onone" | "-O" | "-Osize" | "-Ounchecked"),
+        Language::Clang => matches!(
+            arg,
+            "-O" | "-O0" | "-O1" | "-O2" | "-O3" | "-Os" | "-Oz" | "-Ofast" | "-Og"
+        ),
+    }
+}
+
+fn is_debug_info_flag(arg: &str) -> bool {
+    matches!(arg, "-g" | "-g0" | "-g1" | "-g2" | "-g3" | "-gnone")
+        || arg.starts_with("-gline-")
+        || arg.starts_with("-gdwarf")
+}
+
+/// Picks the audited settings out of a compiler command line
+pub fn compiler_flags(language: Language, command: &BuildCommand) -> CompilerFlags {
+    let mut flags = CompilerFlags::default();
+    for arg in command.args.iter() {
+        if is_optimization_flag(language, arg) {
+            flags.optimization = Some(arg.clone());
+        } else if is_debug_info_flag(arg) {
+            flags.debug_info = Some(arg.clone());
+        } else if arg == "-enable-testing" {
+            flags.enable_testing = true;
+        } else if arg == "-whole-module-optimization" || arg == "-wmo" {
+            flags.whole_module = true;
+        } else if let Some(sanitizers) = arg
+            .strip_prefix("-sanitize=")
+            .or_else(|| arg.strip_prefix("-fsanitize="))
+        {
+            flags
+                .sanitizers
+                .extend(sanitizers.split(',').map(str::to_string));
+        }
+    }
+    flags.sanitizers.sort();
+    flags.sanitizers.dedup();
+    flags
+}
+
+/// Configuration from a path like `.../MyApp.build/Release-iphoneos/...`
+fn path_configuration(command: &BuildCommand) -> Option<String> {
+    command.args.iter().find_map(|arg| {
+        let (_, rest) = arg.split_once(".build/")?;
+        let directory = rest.split('/').next()?;
+        let configuration = directory.split('-').next()?;
+        (!configuration.is_empty()).then(|| configuration.to_string())
+    })
+}
+
+fn is_release(configuration: Option<&str>) -> bool {
+    configuration.is_some_and(|c| c.to_lowercase().contains("release"))
+}
+
+fn is_unoptimized(flags: &CompilerFlags) -> bool {
+    matches!(flags.optimization.as_deref(), Some("-Onone" | "-O0"))
+}
+
+fn release_warnings(target: &TargetFlags) -> Vec<String> {
+    let flags = &target.flags;
+    let mut warnings = vec![];
+    if is_unoptimized(flags) {
+        warnings.push(format!(
+            "compiled without optimization ({})",
+            flags.optimization.as_deref().unwrap_or_default()
+        ));
+    }
+    if flags.optimization.as_deref() == Some("-Ounchecked") {
+        warnings.push("-Ounchecked removes runtime safety checks".to_string());
+    }
+    if flags.enable_testing {
+        warnings.push("-enable-testing exports internal symbols".to_string());
+    }
+    if !flags.sanitizers.is_empty() {
+        warnings.push(format!(
+            "sanitizers enabled: {}",
+            flags.sanitizers.join(",")
+        ));
+    }
+    if target.language == Language::Swift && !flags.whole_module {
+        warnings.push("Swift sources are not built with whole-module optimization".to_string());
+    }
+    warnings
+}
+
+fn flag_values(flags: &CompilerFlags) -> [(&'static str, String); 5] {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "<none>".to_string());
+    [
+        ("optimization", optional(&flags.optimization)),
+        ("enable_testing", flags.enable_testing.to_string()),
+        ("whole_module", flags.whole_module.to_string()),
+        ("sanitizers", flags.sanitizers.join(",")),
+        ("debug_info", optional(&flags.debug_info)),
+    ]
+}
+
+type TargetKey = (String, Option<String>, Language);
+
+/// Audits the flags of all Swift and clang compile steps of the log
+pub fn flag_audit_report(objects: &[XActivityLogObject]) -> FlagAuditReport {
+    let mut per_target = BTreeMap::<TargetKey, BTreeMap<CompilerFlags, usize>>::new();
+    // Whole-module optimization is a setting of the module: only the driver step
+    // carries the flag, the per-file frontend jobs it spawns don't
+    let mut whole_module_targets = BTreeSet::<TargetKey>::new();
+    for node in walk_sections(objects) {
+        let Some(language) = step_language(&node) else {
+            continue;
+        };
+        let Some(command) = node.section.build_command() else {
+            continue;
+        };
+        let configuration = node
+            .configuration()
+            .map(str::to_string)
+            .or_else(|| path_configuration(&command));
+        let target = node.target_name().unwrap_or("<no target>").to_string();
+        let key = (target, configuration, language);
+        let mut flags = compiler_flags(language, &command);
+        if flags.whole_module {
+            whole_module_targets.insert(key.clone());
+            flags.whole_module = false;
+        }
+        *per_target.entry(key).or_default().entry(flags).or_default() += 1;
+    }
+
+    let targets = per_target
+        .into_iter()
+        .map(|(key, counts)| {
+            let whole_module = whole_module_targets.contains(&key);
+            let (target, configuration, language) = key;
+            let mut variants = counts
+                .into_iter()
+                .map(|(mut flags, steps)| {
+                    flags.whole_module = whole_module;
+                    FlagVariant { flags, steps }
+                })
+                .collect::<Vec<_>>();
+            variants.sort_by_key(|v| std::cmp::Reverse(v.steps));
+            let most_common = variants.remove(0);
+            TargetFlags {
+                target,
+                configuration,
+                language,
+                flags: most_common.flags,
+                steps: most_common.steps + variants.iter().map(|v| v.steps).sum::<usize>(),
+                variants,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Per configuration, language and flag: targets by value
+    let mut values =
+        BTreeMap::<(Option<&str>, Language, &'static str), BTreeMap<String, Vec<String>>>::new();
+    for target in targets.iter() {
+        for (flag, value) in flag_values(&target.flags) {
+            values
+                .entry((target.configuration.as_deref(), target.language, flag))
+                .or_default()
+                .entry(value)
+                .or_default()
+                .push(target.target.clone());
+        }
+    }
+    let inconsistencies = values
+        .into_iter()
+        .filter(|(_, by_value)| by_value.len() > 1)
+        .map(
+            |((configuration, language, flag), by_value)| FlagInconsistency {
+                configuration: configuration.map(str::to_string),
+                language,
+                flag,
+                values: by_value
+                    .into_iter()
+                    .map(|(value, targets)| FlagValue { value, targets })
+                    .collect(),
+            },
+        )
+        .collect();
+
+    let release_warnings = targets
+        .iter()
+        .filter(|t| is_release(t.configuration.as_deref()))
+        .flat_map(|target| {
+            release_warnings(target)
+                .into_iter()
+                .map(|message| FlagWarning {
+                    target: target.target.clone(),
+                    configuration: target.configuration.clone(),
+                    language: target.language,
+                    message,
+                })
+        })
+        .collect();
+
+    FlagAuditReport {
+        targets,
+        inconsistencies,
+        release_warnings,
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Language::Swift => write!(f, "swift"),
+            Language::Clang => write!(f, "clang"),
+        }
+    }
+}
+
+fn write_flags_row(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    flags: &CompilerFlags,
+    steps: usize,
+) -> fmt::Result {
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+    let sanitizers = match flags.sanitizers.is_empty() {
+        true => "-".to_string(),
+        false => flags.sanitizers.join(","),
+    };
+    writeln!(
+        f,
+        "  {:<40} {:<12} {:>7} {:>5} {:<12} {:<18} {:>6}",
+        name,
+        flags.optimization.as_deref().unwrap_or("-"),
+        yes_no(flags.enable_testing),
+        yes_no(flags.whole_module),
+        sanitizers,
+        flags.debug_info.as_deref().unwrap_or("-"),
+        steps
+    )
+}
+
+impl fmt::Display for FlagAuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "  {:<40} {:<12} {:>7} {:>5} {:<12} {:<18} {:>6}",
+            "Target", "opt", "testing", "wmo", "sanitizers", "debug info", "steps"
+        )?;
+        for target in self.targets.iter() {
+            let name = format!(
+                "{} [{} {}]",
+                target.target,
+                target.configuration.as_deref().unwrap_or("?"),
+                target.language
+            );
+            write_flags_row(f, &name, &target.flags, target.steps)?;
+            for variant in target.variants.iter() {
+                write_flags_row(f, "    (variant)", &variant.flags, variant.steps)?;
+            }
+        }
+
+        writeln!(f, "\nInconsistencies ({}):", self.inconsistencies.len())?;
+        for inconsistency in self.inconsistencies.iter() {
+            let values = inconsistency
+                .values
+                .iter()
+                .map(|v| format!("{} ({})", v.value, v.targets.join(", ")))
+                .collect::<Vec<_>>();
+            writeln!(
+                f,
+                "  [{} {}] {}: {}",
+                inconsistency.configuration.as_deref().unwrap_or("?"),
+                inconsistency.language,
+                inconsistency.flag,
+                values.join(" vs ")
+            )?;
+        }
+
+        writeln!(f, "\nRelease warnings ({}):", self.release_warnings.len())?;
+        for warning in self.release_warnings.iter() {
+            writeln!(
+                f,
+                "  [{} {}] {}",
+                warning.target, warning.language, warning.message
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_class::IDEActivityLogSection;
+    use crate::log_types::SectionType;
+
+    fn step(signature: &str, command: &str) -> IDEActivityLogSection {
+        let text = format!("{signature}\n    cd /src\n    {command}\n");
+        IDEActivityLogSection::test_step(signature, signature, Some(&text))
+    }
+
+    fn target(
+        name: &str,
+        configuration: &str,
+        steps: Vec<IDEActivityLogSection>,
+    ) -> IDEActivityLogSection {
+        let title =
+            format!("Build target {name} of project App with configuration {configuration}");
+        let mut section = IDEActivityLogSection::test_step(&title, "", None);
+        section.section_type = SectionType::Target;
+        section.sub_sections = steps;
+        section
+    }
+
+    fn swift_compile(target: &str, flags: &str) -> IDEActivityLogSection {
+        step(
+            &format!("SwiftCompile normal arm64 /src/A.swift (in target '{target}' from project 'App')"),
+            &format!("builtin-swiftTaskExecution -- /usr/bin/swift-frontend -frontend -c -primary-file /src/A.swift {flags}"),
+        )
+    }
+
+    fn swift_driver(target: &str, flags: &str) -> IDEActivityLogSection {
+        step(
+            &format!("SwiftDriver {target} normal arm64 com.apple.xcode.tools.swift.compiler (in target '{target}' from project 'App')"),
+            &format!("builtin-SwiftDriver -- /usr/bin/swiftc -module-name {target} {flags} /src/A.swift"),
+        )
+    }
+
+    #[test]
+    fn matches_optimization_flags_exactly() {
+        let command =
+            |args: &str| BuildCommand::parse(&format!("Step\n    cd /src\n    {args}\n")).unwrap();
+        let clang = compiler_flags(Language::Clang, &command("/usr/bin/clang -Os -ObjC -c a.m"));
+        assert_eq!(clang.optimization.as_deref(), Some("-Os"));
+        let clang = compiler_flags(
+            Language::Clang,
+            &command("/usr/bin/clang -ObjC -Ofoo -c a.m"),
+        );
+        assert_eq!(clang.optimization, None);
+        let swift = compiler_flags(Language::Swift, &command("/usr/bin/swiftc -Onone -O -g"));
+        assert_eq!(swift.optimization.as_deref(), Some("-O"));
+        assert_eq!(swift.debug_info.as_deref(), Some("-g"));
+    }
+
+    #[test]
+    fn takes_whole_module_optimization_from_the_driver() {
+        let report = flag_audit_report(&[IDEActivityLogSection::test_build(vec![
+            target(
+                "App",
+                "Release",
+                vec![
+                    swift_driver("App", "-O -whole-module-optimization"),
+                    swift_compile("App", "-O"),
+                    swift_compile("App", "-O"),
+                ],
+            ),
+            target(
+                "Kit",
+                "Release",
+                vec![swift_driver("Kit", "-O"), swift_compile("Kit", "-O")],
+            ),
+        ])
+        .into()]);
+
+        let app = &report.targets[0];
+        assert_eq!(
+            (app.target.as_str(), app.configuration.as_deref()),
+            ("App", Some("Release"))
+        );
+        assert!(app.flags.whole_module);
+        assert_eq!(app.steps, 3);
+        assert!(app.variants.is_empty());
+
+        let warnings = report
+            .release_warnings
+            .iter()
+            .map(|w| (w.target.as_str(), w.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [(
+                "Kit",
+                "Swift sources are not built with whole-module optimization"
+            )]
+        );
+        assert_eq!(report.inconsistencies.len(), 1);
+        assert_eq!(report.inconsistencies[0].flag, "whole_module");
+    }
+
+    #[test]
+    fn reports_inconsistent_targets_and_release_warnings() {
+        let clang = |target: &str, flags: &str| {
+            step(
+                &format!("CompileC /Build/a.o /src/a.m normal arm64 objective-c com.apple.compilers.llvm.clang.1_0.compiler (in target '{target}' from project 'App')"),
+                &format!("/usr/bin/clang -x objective-c {flags} -c /src/a.m -o /Build/a.o"),
+            )
+        };
+        let report = flag_audit_report(&[IDEActivityLogSection::test_build(vec![
+            target(
+                "App",
+                "Release",
+                vec![clang("App", "-O0 -fsanitize=address")],
+            ),
+            target(
+                "Kit",
+                "Release",
+                vec![
+                    clang("Kit", "-Os"),
+                    clang("Kit", "-O0"),
+                    clang("Kit", "-Os"),
+                ],
+            ),
+        ])
+        .into()]);
+
+        let kit = &report.targets[1];
+        assert_eq!(kit.flags.optimization.as_deref(), Some("-Os"));
+        assert_eq!(kit.steps, 3);
+        assert_eq!(kit.variants.len(), 1);
+
+        let flags = report
+            .inconsistencies
+            .iter()
+            .map(|i| i.flag)
+            .collect::<Vec<_>>();
+        assert_eq!(flags, ["optimization", "sanitizers"]);
+        let warnings = report
+            .release_warnings
+            .iter()
+            .filter(|w| w.target == "App")
+            .map(|w| w.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [
+                "compiled without optimization (-O0)",
+                "sanitizers enabled: address"
+            ]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::deser::XActivityLogObject;
+use crate::walk::walk_sections;
+
+/// Type-checking times printed by `-Xfrontend -debug-time-function-bodies`,
+/// merged over all Swift compile steps and sorted by total time
+#[derive(Debug, Serialize)]
+pub struct FunctionBodiesReport {
+    pub function_bodies: Vec<FunctionBodyTime>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionBodyTime {
+    /// `None` for `<invalid loc>` entries
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+    pub declaration: String,
+    /// Sum over all compile steps that reported the body
+    pub total_ms: f64,
+    pub max_ms: f64,
+    pub occurrences: usize,
+}
+
+/// Parses lines like `12.34ms\t/path/File.swift:3:10\tinstance method foo()`,
+/// other lines of the text are ignored
+pub fn parse_function_body_times(text: &str) -> Vec<FunctionBodyTime> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<FunctionBodyTime> {
+    let mut parts = line.trim_end_matches('\r').splitn(3, '\t');
+    let ms = parts
+        .next()?
+        .trim()
+        .strip_suffix("ms")?
+        .parse::<f64>()
+        .ok()?;
+    let location = parts.next()?;
+    let declaration = parts.next()?.trim().to_string();
+
+    let parsed_location = location.rsplit_once(':').and_then(|(rest, column)| {
+        let (file, line) = rest.rsplit_once(':')?;
+        Some((file, line.parse().ok()?, column.parse().ok()?))
+    });
+    let (file, line, column) = match parsed_location {
+        Some((file, line, column)) => (Some(file.to_string()), Some(line), Some(column)),
+        None => (None, None, None),
+    };
+    Some(FunctionBodyTime {
+        file,
+        line,
+        column,
+        declaration,
+        total_ms: ms,
+        max_ms: ms,
+        occurrences: 1,
+    })
+}
+
+type FunctionBodyKey = (Option<String>, Option<u64>, Option<u64>, String);
+
+/// Collects function body times of all Swift compile steps
+pub fn function_bodies_report(objects: &[XActivityLogObject]) -> FunctionBodiesReport {
+    let mut merged = HashMap::<FunctionBodyKey, FunctionBodyTime>::new();
+    let texts = walk_sections(objects)
+        .filter(|node| node.section.step_info().kind.is_swift_compile())
+        .filter_map(|node| node.section.text.as_deref());
+    for time in texts.flat_map(parse_function_body_times) {
+        let key = (
+            time.file.clone(),
+            time.line,
+            time.column,
+            time.declaration.clone(),
+        );
+        match merged.get_mut(&key) {
+            Some(entry) => {
+                entry.total_ms += time.total_ms;
+                entry.max_ms = entry.max_ms.max(time.max_ms);
+                entry.occurrences += 1;
+            }
+            None => {
+                merged.insert(key, time);
+            }
+        }
+    }
+
+    let mut function_bodies = merged.into_values().collect::<Vec<_>>();
+    function_bodies.sort_by(|a, b| {
+        b.total_ms
+            .total_cmp(&a.total_ms)
+            .then_with(|| a.file.cmp(&b.file))
+            .then_with(|| a.line.cmp(&b.line))
+            .then_with(|| a.declaration.cmp(&b.declaration))
+    });
+    FunctionBodiesReport { function_bodies }
+}
+
+impl fmt::Display for FunctionBodiesReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>12} {:>10} {:>6}  {:<60}  declaration",
+            "total", "max", "count", "location"
+        )?;
+        for time in self.function_bodies.iter() {
+            let location = match (&time.file, time.line, time.column) {
+                (Some(file), Some(line), Some(column)) => format!("{file}:{line}:{column}"),
+                _ => "<invalid loc>".to_string(),
+            };
+            writeln!(
+                f,
+                "{:>10.2}ms {:>8.2}ms {:>6}  {:<60}  {}",
+                time.total_ms, time.max_ms, time.occurrences, location, time.declaration
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_class::IDEActivityLogSection;
+
+    #[test]
+    fn parses_times_and_skips_other_lines() {
+        let text = "CompileSwift normal arm64 /src/App.swift\n\
+            12.34ms\t/src/App.swift:3:10\tinstance method foo()\r\n\
+            0.00ms\t/src/App.swift:20:5\tgetter bar\n\
+            note: something else\n";
+        let times = parse_function_body_times(text);
+        assert_eq!(times.len(), 2);
+        assert_eq!(times[0].file.as_deref(), Some("/src/App.swift"));
+        assert_eq!((times[0].line, times[0].column), (Some(3), Some(10)));
+        assert_eq!(times[0].declaration, "instance method foo()");
+        assert_eq!(times[0].total_ms, 12.34);
+        assert_eq!(times[1].total_ms, 0.0);
+        assert_eq!(times[1].declaration, "getter bar");
+    }
+
+    #[test]
+    fn keeps_times_without_location() {
+        let times = parse_function_body_times("0.05ms\t<invalid loc>\timplicit closure #1\n");
+        assert_eq!(times.len(), 1);
+        assert_eq!(times[0].file, None);
+        assert_eq!((times[0].line, times[0].column), (None, None));
+        assert_eq!(times[0].declaration, "implicit closure #1");
+        assert_eq!(times[0].occurrences, 1);
+    }
+
+    #[test]
+    fn collects_times_of_batch_and_whole_module_steps() {
+        let step = |signature: &str, text: &str| {
+            XActivityLogObject::IDEActivityLogSection(IDEActivityLogSection::test_step(
+                signature,
+                signature,
+                Some(text),
+            ))
+        };
+        let objects = [
+            step(
+                "SwiftCompile normal arm64 /src/App.swift (in target 'App' from project 'App')",
+                "2.00ms\t/src/App.swift:3:10\tinstance method foo()\n",
+            ),
+            step(
+                "CompileSwiftSources normal arm64 com.apple.xcode.tools.swift.compiler (in target 'App' from project 'App')",
+                "5.00ms\t/src/App.swift:3:10\tinstance method foo()\n",
+            ),
+            step(
+                "Ld /Build/App normal (in target 'App' from project 'App')",
+                "9.00ms\t/src/App.swift:3:10\tinstance method foo()\n",
+            ),
+        ];
+        let report = function_bodies_report(&objects);
+        assert_eq!(report.function_bodies.len(), 1);
+        let foo = &report.function_bodies[0];
+        assert_eq!((foo.total_ms, foo.max_ms, foo.occurrences), (7.0, 5.0, 2));
+    }
+
+    #[test]
+    fn ignores_incomplete_lines() {
+        assert!(parse_function_body_times("1.0ms\t/src/App.swift:1:1").is_empty());
+        assert!(parse_function_body_times("fast\t/src/App.swift:1:1\tfoo()").is_empty());
+        assert!(parse_function_body_times("").is_empty());
+    }
+}
@@ -1,14 +1,86 @@
 use flate2::read::GzDecoder;
+use memmap2::Mmap;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Deref;
 use std::{fs::File, io, path::PathBuf};
 
+pub mod attachment;
+pub mod cache;
+pub mod command;
+pub mod compile_db;
+pub mod critical_path;
 pub mod deser;
+pub mod diff;
+pub mod events;
 pub mod export;
-mod log_class;
+pub mod flag_audit;
+pub mod function_bodies;
+pub mod log_class;
+pub mod log_store;
+pub mod log_types;
+pub mod markdown;
 pub mod parser;
-mod token;
+pub mod redact;
+pub mod render;
+pub mod resources;
+pub mod select;
+#[doc(hidden)]
+pub mod slf_writer;
+pub mod step_kind;
+pub mod summary;
+pub mod token;
+pub mod tree;
+pub mod type_check;
+pub mod walk;
+pub mod why_failed;
 
 /// Reads a gzipped file
 pub fn read_gzipped_file(path: &PathBuf) -> io::Result<GzDecoder<File>> {
     let file = File::open(path)?;
     Ok(GzDecoder::new(file))
 }
+
+/// Parses a gzipped activity log into its top level objects
+pub fn read_log(path: &PathBuf) -> anyhow::Result<Vec<deser::XActivityLogObject>> {
+    let contents = read_gzipped_file(path)?;
+    let mut parser = parser::Parser::new(contents);
+    let mut tokens = parser.iter().peekable();
+    Ok(deser::Deserializer::new(&mut tokens).collect())
+}
+
+/// Activity log contents held in memory, see [`parser::SliceParser`]
+pub enum LogBuffer {
+    Decompressed(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for LogBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            LogBuffer::Decompressed(v) => v,
+            LogBuffer::Mapped(m) => m,
+        }
+    }
+}
+
+/// Loads a log into memory.
+/// Gzipped `.xcactivitylog` files are decompressed, uncompressed SLF files are memory-mapped.
+pub fn load_log(path: &PathBuf) -> io::Result<LogBuffer> {
+    let mut file = File::open(path)?;
+    let mut magic = [0; 2];
+    let is_gzipped = file.read_exact(&mut magic).is_ok() && magic == [0x1f, 0x8b];
+    file.seek(SeekFrom::Start(0))?;
+
+    if is_gzipped {
+        let mut contents = vec![];
+        GzDecoder::new(file).read_to_end(&mut contents)?;
+        Ok(LogBuffer::Decompressed(contents))
+    } else {
+        // SAFETY: the mapping is read-only. Logs are not expected to be modified
+        // while they are parsed, which is the same assumption `File` based parsing makes.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(LogBuffer::Mapped(mmap))
+    }
+}
@@ -1,11 +1,15 @@
 use std::iter::Peekable;
 
+use crate::attachment::{AttachmentPayload, TaskMetrics};
+use crate::command::BuildCommand;
+use crate::log_types::{DomainType, SectionType, Severity};
+use crate::step_kind::{self, StepInfo};
 use crate::token::Token;
 use serde::Serialize;
 use serde_json::Value;
 use std::error::Error;
 use std::fmt;
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime};
 
 // Define a custom error type
 #[derive(Debug)]
@@ -104,8 +108,8 @@ where
 
 #[derive(Debug, Serialize)]
 pub struct IDECommandLineBuildLog {
-    pub section_type: i8,
-    pub domain_type: String,
+    pub section_type: SectionType,
+    pub domain_type: DomainType,
     pub title: String,
     pub signature: String,
     #[serde(with = "time::serde::iso8601")]
@@ -128,8 +132,8 @@ where
             <Self as XActivityLogClass<Peekable<T>>>::get_possible_class_names()
         );
         let _class_instance = usize::try_from(read_token!(tokens)?)?;
-        let section_type = i8::try_from(read_token!(tokens)?)?;
-        let domain_type = String::try_from(read_token!(tokens)?)?;
+        let section_type = SectionType::from(i8::try_from(read_token!(tokens)?)?);
+        let domain_type = DomainType::from(String::try_from(read_token!(tokens)?)?);
         let title = String::try_from(read_token!(tokens)?)?;
         let signature = String::try_from(read_token!(tokens)?)?;
         let time_started_recording = OffsetDateTime::try_from(read_token!(tokens)?)?;
@@ -171,8 +175,8 @@ where
 
 #[derive(Debug, Serialize)]
 pub struct IDEActivityLogSection {
-    pub section_type: i8,
-    pub domain_type: String,
+    pub section_type: SectionType,
+    pub domain_type: DomainType,
     pub title: String,
     pub signature: String,
     #[serde(with = "time::serde::iso8601")]
@@ -195,6 +199,120 @@ pub struct IDEActivityLogSection {
     pub unknown: Option<u64>,
 }
 
+impl IDEActivityLogSection {
+    pub fn duration(&self) -> Duration {
+        self.time_stopped_recording - self.time_started_recording
+    }
+
+    /// Kind of the step with the arguments of its signature
+    pub fn step_info(&self) -> StepInfo {
+        step_kind::classify(self)
+    }
+
+    /// CPU time and memory of the step from its `TaskMetrics` attachment
+    pub fn task_metrics(&self) -> Option<TaskMetrics> {
+        self.attachments
+            .iter()
+            .find_map(|attachment| match attachment.decode() {
+                Ok(AttachmentPayload::TaskMetrics(metrics)) => Some(metrics),
+                Ok(_) => None,
+                Err(e) => {
+                    log::warn!("Failed to decode {}: {e}", attachment.identifier);
+                    None
+                }
+            })
+    }
+
+    /// Command line the step ran, parsed from its text
+    pub fn build_command(&self) -> Option<BuildCommand> {
+        self.text.as_deref().and_then(BuildCommand::parse)
+    }
+}
+
+#[cfg(test)]
+impl IDEActivityLogSection {
+    /// Build step section with the given title, signature and text, for tests
+    pub(crate) fn test_step(title: &str, signature: &str, text: Option<&str>) -> Self {
+        let start = time::macros::datetime!(2001-01-01 0:00 UTC);
+        IDEActivityLogSection {
+            section_type: SectionType::Command,
+            domain_type: DomainType::BuildStep,
+            title: title.to_string(),
+            signature: signature.to_string(),
+            time_started_recording: start,
+            time_stopped_recording: start + Duration::seconds(1),
+            sub_sections: vec![],
+            text: text.map(str::to_string),
+            messages: vec![],
+            was_cancelled: false,
+            is_quiet: false,
+            was_fetched_from_cache: false,
+            subtitle: None,
+            location: None,
+            command_details_spect: None,
+            unique_identifier: None,
+            localized_result_string: None,
+            xcbuild_signature: None,
+            attachments: vec![],
+            unknown: None,
+        }
+    }
+
+    /// Step of `target` in project `App` taking `seconds`, titled with `signature`
+    /// and with the `(in target ...)` suffix Xcode adds to the signature
+    pub(crate) fn test_step_in(target: &str, signature: &str, seconds: i64) -> Self {
+        let mut step = Self::test_step(
+            signature,
+            &format!("{signature} (in target '{target}' from project 'App')"),
+            None,
+        );
+        step.time_stopped_recording = step.time_started_recording + Duration::seconds(seconds);
+        step
+    }
+
+    /// Main section `Build App` holding `sections`, spanning their times
+    pub(crate) fn test_build(sections: Vec<Self>) -> Self {
+        let mut build = Self::test_group("Build App", sections);
+        build.section_type = SectionType::Main;
+        build.domain_type = DomainType::Build;
+        build
+    }
+
+    /// The section moved `seconds` later
+    pub(crate) fn test_shifted(mut self, seconds: i64) -> Self {
+        self.time_started_recording += Duration::seconds(seconds);
+        self.time_stopped_recording += Duration::seconds(seconds);
+        self
+    }
+
+    /// Section of the target `name` in project `App` holding `steps`, spanning their times
+    pub(crate) fn test_target(name: &str, steps: Vec<Self>) -> Self {
+        let title = format!("Build target {name} of project App with configuration Debug");
+        let mut target = Self::test_group(&title, steps);
+        target.section_type = SectionType::Target;
+        target
+    }
+
+    fn test_group(title: &str, sub_sections: Vec<Self>) -> Self {
+        let mut group = Self::test_step(title, "", None);
+        if let Some(start) = sub_sections.iter().map(|s| s.time_started_recording).min() {
+            group.time_started_recording = start;
+        }
+        if let Some(end) = sub_sections.iter().map(|s| s.time_stopped_recording).max() {
+            group.time_stopped_recording = end;
+        }
+        group.sub_sections = sub_sections;
+        group
+    }
+}
+
+#[cfg(test)]
+impl From<IDEActivityLogSection> for crate::deser::XActivityLogObject {
+    fn from(section: IDEActivityLogSection) -> Self {
+        crate::deser::XActivityLogObject::IDEActivityLogSection(section)
+    }
+}
+
 impl<T> XActivityLogClass<Peekable<T>> for IDEActivityLogSection
 where
     T: Iterator<Item = Token>,
@@ -209,8 +327,8 @@ where
             &class_position_to_name[class_instance - 1]
         );
 
-        let section_type = i8::try_from(read_token!(tokens)?)?;
-        let domain_type = String::try_from(read_token!(tokens)?)?;
+        let section_type = SectionType::from(i8::try_from(read_token!(tokens)?)?);
+        let domain_type = DomainType::from(String::try_from(read_token!(tokens)?)?);
         let title = String::try_from(read_token!(tokens)?)?;
         let signature = String::try_from(read_token!(tokens)?)?;
         let time_started_recording = OffsetDateTime::try_from(read_token!(tokens)?)?;
@@ -219,7 +337,11 @@ where
         let sub_sections = deser_vec(tokens, sub_sections_size, class_position_to_name);
         let text = Option::<String>::try_from(read_token!(tokens)?)?;
         let messages_size = Option::<usize>::try_from(read_token!(tokens)?)?.unwrap_or(0);
-        let messages = deser_vec(tokens, messages_size, class_position_to_name);
+        let mut messages: Vec<IDEActivityLogMessage> =
+            deser_vec(tokens, messages_size, class_position_to_name);
+        for message in messages.iter_mut() {
+            message.resolve_section_text(text.as_deref());
+        }
         let was_cancelled = bool::try_from(read_token!(tokens)?)?;
         let is_quiet = bool::try_from(read_token!(tokens)?)?;
         let was_fetched_from_cache = bool::try_from(read_token!(tokens)?)?;
@@ -314,20 +436,98 @@ where
     }
 }
 
-#[derive(Default, Debug, Serialize)]
+#[derive(Debug, Serialize)]
 pub struct IDEActivityLogMessage {
     pub title: String,
     pub short_title: Option<String>,
-    pub time_emitted: u64,
+    #[serde(with = "time::serde::iso8601")]
+    pub time_emitted: OffsetDateTime,
     pub range_end_in_section_text: u64,
     pub range_start_in_section_text: u64,
     pub sub_messages: Vec<IDEActivityLogMessage>,
-    pub severity: i32,
+    pub severity: Severity,
     pub r#type: Option<String>,
     pub location: Option<DVTDocumentLocation>,
     pub category_ident: Option<String>,
     pub secondary_locations: Vec<DVTDocumentLocation>,
     pub additional_description: Option<String>,
+    /// Part of the section text the message points to, e.g. the compiler output
+    /// that produced the diagnostic. Not part of the log, resolved from the ranges.
+    pub section_text: Option<String>,
+}
+
+#[cfg(test)]
+impl IDEActivityLogMessage {
+    /// Message with the given title and severity, for tests
+    pub(crate) fn test_message(title: &str, severity: Severity) -> Self {
+        IDEActivityLogMessage {
+            title: title.to_string(),
+            short_title: None,
+            time_emitted: time::macros::datetime!(2001-01-01 0:00 UTC),
+            range_end_in_section_text: 0,
+            range_start_in_section_text: 0,
+            sub_messages: vec![],
+            severity,
+            r#type: None,
+            location: None,
+            category_ident: None,
+            secondary_locations: vec![],
+            additional_description: None,
+            section_text: None,
+        }
+    }
+}
+
+/// Slice of `text` between two offsets counted in UTF-16 code units, like
+/// `NSString` does. `None` if the range is empty, out of bounds or splits a character.
+pub fn utf16_slice(text: &str, start: u64, end: u64) -> Option<&str> {
+    if start >= end {
+        return None;
+    }
+    let mut start_byte = None;
+    let mut position = 0u64;
+    for (byte, c) in text.char_indices() {
+        if position == start {
+            start_byte = Some(byte);
+        }
+        if position == end {
+            return Some(&text[start_byte?..byte]);
+        }
+        if position > end {
+            return None;
+        }
+        position += c.len_utf16() as u64;
+    }
+    match position == end {
+        true => Some(&text[start_byte?..]),
+        false => None,
+    }
+}
+
+impl IDEActivityLogMessage {
+    /// Fills `section_text` of the message and its sub messages from the text of their section
+    pub fn resolve_section_text(&mut self, text: Option<&str>) {
+        self.section_text = text
+            .and_then(|text| {
+                utf16_slice(
+                    text,
+                    self.range_start_in_section_text,
+                    self.range_end_in_section_text,
+                )
+            })
+            .map(str::to_string);
+        for sub_message in self.sub_messages.iter_mut() {
+            sub_message.resolve_section_text(text);
+        }
+    }
+
+    pub fn is_warning(&self) -> bool {
+        self.severity == Severity::Warning
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
 }
 
 impl<T> XActivityLogClass<Peekable<T>> for IDEActivityLogMessage
@@ -345,12 +545,12 @@ where
         let _class_instance = usize::try_from(read_token!(tokens)?)?;
         let title = String::try_from(read_token!(tokens)?)?;
         let short_title = Option::<String>::try_from(read_token!(tokens)?)?;
-        let time_emitted = u64::try_from(read_token!(tokens)?)?;
+        let time_emitted = OffsetDateTime::try_from(read_token!(tokens)?)?;
         let range_end_in_section_text = u64::try_from(read_token!(tokens)?)?;
         let range_start_in_section_text = u64::try_from(read_token!(tokens)?)?;
         let sub_messages_size = Option::<usize>::try_from(read_token!(tokens)?)?.unwrap_or(0);
         let sub_messages = deser_vec(tokens, sub_messages_size, class_position_to_name);
-        let severity = i32::try_from(read_token!(tokens)?)?;
+        let severity = Severity::from(i32::try_from(read_token!(tokens)?)?);
         let r#type = Option::<String>::try_from(read_token!(tokens)?)?;
         let location = deser_exact::<DVTDocumentLocation, _>(tokens, class_position_to_name);
         let category_ident = Option::<String>::try_from(read_token!(tokens)?)?;
@@ -373,6 +573,7 @@ where
             category_ident,
             secondary_locations,
             additional_description,
+            section_text: None,
         })
     }
 
@@ -506,7 +707,7 @@ where
             <Self as XActivityLogClass<Peekable<T>>>::get_possible_class_names()
         );
         let class_instance = usize::try_from(read_token!(tokens)?)?;
-        let class_name = &class_position_to_name[class_instance - 1];
+        let class_name = class_position_to_name[class_instance - 1].clone();
 
         let document_url_string = String::try_from(read_token!(tokens)?)?;
         let timestamp = f64::try_from(read_token!(tokens)?)?;
@@ -518,16 +719,20 @@ where
 
         Ok(match class_name.as_str() {
             "DVTDocumentLocation" => DVTDocumentLocation::Base(base),
-            "DVTTextDocumentLocation" => {
-                for _ in 0..7 {
-                    tokens.next();
-                }
-                DVTDocumentLocation::Text(DVTTextDocumentLocation::default())
-            }
-            "DVTMemberDocumentLocation" => {
-                tokens.next();
-                DVTDocumentLocation::Member(DVTMemberDocumentLocation::default())
-            }
+            "DVTTextDocumentLocation" => DVTDocumentLocation::Text(DVTTextDocumentLocation {
+                base,
+                starting_line_number: u64::try_from(read_token!(tokens)?)?,
+                starting_column_number: u64::try_from(read_token!(tokens)?)?,
+                ending_line_number: u64::try_from(read_token!(tokens)?)?,
+                ending_column_number: u64::try_from(read_token!(tokens)?)?,
+                character_range_end: u64::try_from(read_token!(tokens)?)?,
+                character_range_start: u64::try_from(read_token!(tokens)?)?,
+                location_encoding: u64::try_from(read_token!(tokens)?)?,
+            }),
+            "DVTMemberDocumentLocation" => DVTDocumentLocation::Member(DVTMemberDocumentLocation {
+                base,
+                member: String::try_from(read_token!(tokens)?)?,
+            }),
             _ => panic!("Unknwon class name"),
         })
     }
@@ -541,6 +746,33 @@ where
     }
 }
 
+impl DVTDocumentLocation {
+    pub fn base(&self) -> &DVTBaseDocumentLocation {
+        match self {
+            DVTDocumentLocation::Base(base) => base,
+            DVTDocumentLocation::Text(text) => &text.base,
+            DVTDocumentLocation::Member(member) => &member.base,
+        }
+    }
+
+    /// Path of the document with the `file://` scheme removed
+    pub fn file_path(&self) -> &str {
+        let url = &self.base().document_url_string;
+        url.strip_prefix("file://").unwrap_or(url)
+    }
+
+    /// One-based line and column, logs store them zero-based
+    pub fn line_and_column(&self) -> Option<(u64, u64)> {
+        match self {
+            DVTDocumentLocation::Text(text) => Some((
+                text.starting_line_number.saturating_add(1),
+                text.starting_column_number.saturating_add(1),
+            )),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Default, Debug, Serialize)]
 pub struct DVTBaseDocumentLocation {
     pub document_url_string: String,
@@ -567,8 +799,8 @@ pub struct DVTMemberDocumentLocation {
 
 #[derive(Debug, Serialize)]
 pub struct IDEActivityLogCommandInvocationSection {
-    pub section_type: i8,
-    pub domain_type: String,
+    pub section_type: SectionType,
+    pub domain_type: DomainType,
     pub title: String,
     pub signature: String,
     #[serde(with = "time::serde::iso8601")]
@@ -594,8 +826,8 @@ where
             <Self as XActivityLogClass<Peekable<T>>>::get_possible_class_names()
         );
         let _class_instance = usize::try_from(read_token!(tokens)?)?;
-        let section_type = i8::try_from(read_token!(tokens)?)?;
-        let domain_type = String::try_from(read_token!(tokens)?)?;
+        let section_type = SectionType::from(i8::try_from(read_token!(tokens)?)?);
+        let domain_type = DomainType::from(String::try_from(read_token!(tokens)?)?);
         let title = String::try_from(read_token!(tokens)?)?;
         let signature = String::try_from(read_token!(tokens)?)?;
         let time_started_recording = OffsetDateTime::try_from(read_token!(tokens)?)?;
@@ -604,7 +836,11 @@ where
         let sub_sections = deser_vec(tokens, sub_sections_size, class_position_to_name);
         let text = Option::<String>::try_from(read_token!(tokens)?)?;
         let messages_size = Option::<usize>::try_from(read_token!(tokens)?)?.unwrap_or(0);
-        let messages = deser_vec(tokens, messages_size, class_position_to_name);
+        let mut messages: Vec<IDEActivityLogMessage> =
+            deser_vec(tokens, messages_size, class_position_to_name);
+        for message in messages.iter_mut() {
+            message.resolve_section_text(text.as_deref());
+        }
         let was_cancelled = bool::try_from(read_token!(tokens)?)?;
 
         Ok(Self {
@@ -629,3 +865,37 @@ where
         todo!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slices_by_utf16_offsets() {
+        let text = "a😀b: error";
+        assert_eq!(utf16_slice(text, 0, 1), Some("a"));
+        // The emoji is a surrogate pair, two UTF-16 code units
+        assert_eq!(utf16_slice(text, 1, 3), Some("😀"));
+        assert_eq!(utf16_slice(text, 3, 4), Some("b"));
+        assert_eq!(utf16_slice(text, 6, 11), Some("error"));
+        assert_eq!(utf16_slice(text, 0, 11), Some(text));
+    }
+
+    #[test]
+    fn rejects_ranges_splitting_a_surrogate_pair() {
+        let text = "a😀b";
+        assert_eq!(utf16_slice(text, 1, 2), None);
+        assert_eq!(utf16_slice(text, 2, 4), None);
+    }
+
+    #[test]
+    fn rejects_empty_reversed_and_out_of_range() {
+        let text = "a😀b";
+        assert_eq!(utf16_slice(text, 2, 2), None);
+        assert_eq!(utf16_slice(text, 3, 1), None);
+        assert_eq!(utf16_slice(text, 0, 5), None);
+        assert_eq!(utf16_slice(text, 10, 20), None);
+        assert_eq!(utf16_slice(text, 0, u64::MAX), None);
+        assert_eq!(utf16_slice("", 0, 1), None);
+    }
+}
@@ -0,0 +1,308 @@
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+use crate::token::datetime_from_reference_seconds;
+
+/// Name of the index Xcode keeps next to the UUID named logs
+pub const MANIFEST_FILE_NAME: &str = "LogStoreManifest.plist";
+
+/// Raw layout of `LogStoreManifest.plist`
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(rename = "logFormatVersion")]
+    log_format_version: Option<u64>,
+    /// Entries are decoded one by one, so that a broken entry doesn't hide the others
+    #[serde(default)]
+    logs: HashMap<String, plist::Value>,
+}
+
+#[derive(Deserialize)]
+struct ManifestLog {
+    #[serde(rename = "className")]
+    class_name: Option<String>,
+    #[serde(rename = "domainType")]
+    domain_type: Option<String>,
+    #[serde(rename = "fileName")]
+    file_name: String,
+    #[serde(rename = "primaryObservable")]
+    primary_observable: Option<PrimaryObservable>,
+    #[serde(rename = "schemeIdentifier-containerName")]
+    container_name: Option<String>,
+    #[serde(rename = "schemeIdentifier-schemeName")]
+    scheme_name: Option<String>,
+    signature: Option<String>,
+    #[serde(rename = "timeStartedRecording")]
+    time_started_recording: f64,
+    #[serde(rename = "timeStoppedRecording")]
+    time_stopped_recording: f64,
+    title: Option<String>,
+    #[serde(rename = "uniqueIdentifier")]
+    unique_identifier: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PrimaryObservable {
+    #[serde(rename = "highLevelStatus")]
+    high_level_status: Option<String>,
+    #[serde(rename = "totalNumberOfErrors")]
+    total_number_of_errors: Option<u64>,
+    #[serde(rename = "totalNumberOfWarnings")]
+    total_number_of_warnings: Option<u64>,
+    #[serde(rename = "totalNumberOfAnalyzerIssues")]
+    total_number_of_analyzer_issues: Option<u64>,
+}
+
+/// Log listed in `LogStoreManifest.plist`
+#[derive(Debug, Serialize)]
+pub struct LogStoreEntry {
+    pub unique_identifier: String,
+    pub file_name: String,
+    /// Absolute location of the `.xcactivitylog` file
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub signature: Option<String>,
+    pub scheme: Option<String>,
+    pub container: Option<String>,
+    pub class_name: Option<String>,
+    pub domain_type: Option<String>,
+    #[serde(with = "time::serde::iso8601")]
+    pub time_started_recording: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    pub time_stopped_recording: OffsetDateTime,
+    /// Single letter status shown by Xcode, e.g. `S` (succeeded), `W` (warnings), `E` (errors)
+    pub status: Option<String>,
+    pub errors: Option<u64>,
+    pub warnings: Option<u64>,
+    pub analyzer_issues: Option<u64>,
+}
+
+impl LogStoreEntry {
+    /// Entry of the manifest under `key`, for a log in `directory`
+    fn from_manifest(directory: &Path, key: String, value: plist::Value) -> anyhow::Result<Self> {
+        let log: ManifestLog = plist::from_value(&value)?;
+        let time = |seconds: f64| {
+            datetime_from_reference_seconds(seconds)
+                .ok_or_else(|| anyhow::anyhow!("Invalid time {seconds}"))
+        };
+        let observable = log.primary_observable;
+        Ok(LogStoreEntry {
+            unique_identifier: log.unique_identifier.unwrap_or(key),
+            path: directory.join(&log.file_name),
+            file_name: log.file_name,
+            title: log.title,
+            signature: log.signature,
+            scheme: log.scheme_name,
+            container: log.container_name,
+            class_name: log.class_name,
+            domain_type: log.domain_type,
+            time_started_recording: time(log.time_started_recording)?,
+            time_stopped_recording: time(log.time_stopped_recording)?,
+            status: observable
+                .as_ref()
+                .and_then(|o| o.high_level_status.clone()),
+            errors: observable.as_ref().and_then(|o| o.total_number_of_errors),
+            warnings: observable.as_ref().and_then(|o| o.total_number_of_warnings),
+            analyzer_issues: observable
+                .as_ref()
+                .and_then(|o| o.total_number_of_analyzer_issues),
+        })
+    }
+}
+
+/// Logs of a `DerivedData/<Project>/Logs/<Kind>` directory
+#[derive(Debug, Serialize)]
+pub struct LogStore {
+    pub directory: PathBuf,
+    pub log_format_version: Option<u64>,
+    /// Entries ordered from the oldest to the newest
+    pub logs: Vec<LogStoreEntry>,
+}
+
+impl LogStore {
+    /// Opens a directory with `LogStoreManifest.plist`.
+    /// `DerivedData/<Project>` and `DerivedData/<Project>/Logs` are accepted too,
+    /// in that case build logs from `Logs/Build` are used.
+    pub fn open(directory: &Path) -> anyhow::Result<Self> {
+        let Some(directory) = [
+            directory.to_path_buf(),
+            directory.join("Build"),
+            directory.join("Logs").join("Build"),
+        ]
+        .into_iter()
+        .find(|d| d.join(MANIFEST_FILE_NAME).is_file()) else {
+            bail!(
+                "No {MANIFEST_FILE_NAME} found in {}",
+                directory.to_string_lossy()
+            );
+        };
+
+        let manifest_path = directory.join(MANIFEST_FILE_NAME);
+        let manifest: Manifest = plist::from_file(&manifest_path)
+            .with_context(|| format!("Failed to read {}", manifest_path.to_string_lossy()))?;
+        log::info!(
+            "Found {} logs in {}",
+            manifest.logs.len(),
+            manifest_path.to_string_lossy()
+        );
+
+        let mut logs = manifest
+            .logs
+            .into_iter()
+            .filter_map(|(key, value)| {
+                match LogStoreEntry::from_manifest(&directory, key.clone(), value) {
+                    Ok(entry) => Some(entry),
+                    Err(e) => {
+                        log::warn!(
+                            "Skipping log {key} of {}: {e:#}",
+                            manifest_path.to_string_lossy()
+                        );
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        // The manifest is a dictionary without order, logs started at the same time
+        // are ordered by their identifier to be the same on every run
+        logs.sort_by(|a, b| {
+            a.time_started_recording
+                .cmp(&b.time_started_recording)
+                .then_with(|| a.unique_identifier.cmp(&b.unique_identifier))
+        });
+
+        Ok(Self {
+            directory,
+            log_format_version: manifest.log_format_version,
+            logs,
+        })
+    }
+
+    pub fn newest(&self) -> Option<&LogStoreEntry> {
+        self.logs.last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn entry(key: &str, file_name: &str, started: &str, scheme: &str) -> String {
+        format!(
+            "<key>{key}</key>
+            <dict>
+                <key>fileName</key><string>{file_name}</string>
+                <key>schemeIdentifier-schemeName</key><string>{scheme}</string>
+                <key>timeStartedRecording</key><real>{started}</real>
+                <key>timeStoppedRecording</key><real>{started}</real>
+                <key>title</key><string>Build {scheme}</string>
+                <key>primaryObservable</key>
+                <dict>
+                    <key>highLevelStatus</key><string>E</string>
+                    <key>totalNumberOfErrors</key><integer>2</integer>
+                </dict>
+            </dict>"
+        )
+    }
+
+    /// `Logs/Build` directory with a manifest holding `entries`
+    fn logs_directory(name: &str, entries: &[String]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "swift_tool_box-log_store-{name}-{}",
+            std::process::id()
+        ));
+        let directory = root.join("Logs").join("Build");
+        fs::create_dir_all(&directory).unwrap();
+        let manifest = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <plist version="1.0">
+            <dict>
+                <key>logFormatVersion</key><integer>11</integer>
+                <key>logs</key>
+                <dict>{}</dict>
+            </dict>
+            </plist>"#,
+            entries.concat()
+        );
+        fs::write(directory.join(MANIFEST_FILE_NAME), manifest).unwrap();
+        root
+    }
+
+    #[test]
+    fn reads_entries_from_oldest_to_newest() {
+        let root = logs_directory(
+            "order",
+            &[
+                entry("B", "B.xcactivitylog", "700000100.5", "Newer"),
+                entry("A", "A.xcactivitylog", "700000000", "Older"),
+            ],
+        );
+        let store = LogStore::open(&root).unwrap();
+        assert_eq!(store.directory, root.join("Logs").join("Build"));
+        assert_eq!(store.log_format_version, Some(11));
+        let schemes = store
+            .logs
+            .iter()
+            .map(|l| l.scheme.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(schemes, [Some("Older"), Some("Newer")]);
+
+        let newest = store.newest().unwrap();
+        assert_eq!(newest.unique_identifier, "B");
+        assert_eq!(newest.path, store.directory.join("B.xcactivitylog"));
+        assert_eq!(newest.status.as_deref(), Some("E"));
+        assert_eq!(newest.errors, Some(2));
+        assert_eq!(
+            newest.time_started_recording,
+            time::macros::datetime!(2023-03-08 20:28:20.5 UTC)
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn orders_logs_started_at_the_same_time_by_identifier() {
+        let entries = ["D", "B", "E", "A", "C"]
+            .map(|key| entry(key, &format!("{key}.xcactivitylog"), "700000000", "MyApp"));
+        let root = logs_directory("ties", &entries);
+        let store = LogStore::open(&root).unwrap();
+        let ids = store
+            .logs
+            .iter()
+            .map(|l| l.unique_identifier.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["A", "B", "C", "D", "E"]);
+        assert_eq!(store.newest().unwrap().unique_identifier, "E");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn skips_broken_entries() {
+        let root = logs_directory(
+            "broken",
+            &[
+                entry("Good", "Good.xcactivitylog", "700000000", "MyApp"),
+                entry("Time", "Time.xcactivitylog", "1e300", "MyApp"),
+                "<key>NoFile</key><dict><key>title</key><string>x</string></dict>".to_string(),
+            ],
+        );
+        let store = LogStore::open(&root).unwrap();
+        let ids = store
+            .logs
+            .iter()
+            .map(|l| l.unique_identifier.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["Good"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn fails_without_manifest() {
+        let root = std::env::temp_dir().join(format!(
+            "swift_tool_box-log_store-missing-{}",
+            std::process::id()
+        ));
+        assert!(LogStore::open(&root).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// Level of a section in the log tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SectionType {
+    /// The whole build, clean or test run
+    Main,
+    Target,
+    /// A build step, e.g. a compile or link command
+    Command,
+    Unknown(i8),
+}
+
+impl From<i8> for SectionType {
+    fn from(raw: i8) -> Self {
+        match raw {
+            0 => SectionType::Main,
+            1 => SectionType::Target,
+            2 => SectionType::Command,
+            raw => SectionType::Unknown(raw),
+        }
+    }
+}
+
+impl SectionType {
+    pub fn raw(&self) -> i8 {
+        match self {
+            SectionType::Main => 0,
+            SectionType::Target => 1,
+            SectionType::Command => 2,
+            SectionType::Unknown(raw) => *raw,
+        }
+    }
+}
+
+impl fmt::Display for SectionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SectionType::Main => write!(f, "main"),
+            SectionType::Target => write!(f, "target"),
+            SectionType::Command => write!(f, "command"),
+            SectionType::Unknown(raw) => write!(f, "unknown({raw})"),
+        }
+    }
+}
+
+/// Serialized by name, unknown types as `unknown(7)`, so the field is always a string
+impl Serialize for SectionType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    #[default]
+    Note,
+    Warning,
+    Error,
+    /// Issue found by the static analyzer
+    Analyzer,
+    Unknown(i32),
+}
+
+impl From<i32> for Severity {
+    fn from(raw: i32) -> Self {
+        match raw {
+            0 => Severity::Note,
+            1 => Severity::Warning,
+            2 => Severity::Error,
+            3 => Severity::Analyzer,
+            raw => Severity::Unknown(raw),
+        }
+    }
+}
+
+impl Severity {
+    pub fn raw(&self) -> i32 {
+        match self {
+            Severity::Note => 0,
+            Severity::Warning => 1,
+            Severity::Error => 2,
+            Severity::Analyzer => 3,
+            Severity::Unknown(raw) => *raw,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+            Severity::Analyzer => write!(f, "analyzer"),
+            Severity::Unknown(raw) => write!(f, "unknown({raw})"),
+        }
+    }
+}
+
+/// Serialized by name, unknown severities as `unknown(7)`, so the field is always a string
+impl Serialize for Severity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+const DOMAIN_TYPE_PREFIX: &str = "Xcode.IDEActivityLogDomainType.";
+const TARGET_DOMAIN_TYPE_PREFIX: &str = "Xcode.IDEActivityLogDomainType.target.product-type.";
+
+/// What a section is about, e.g. `Xcode.IDEActivityLogDomainType.BuildLog`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DomainType {
+    Build,
+    Clean,
+    Test,
+    Archive,
+    /// Target of the given product type, e.g. `framework` or `application`
+    Target(String),
+    /// `com.apple.dt.IDE.BuildLogSection`, the domain of build steps
+    BuildStep,
+    Unknown(String),
+}
+
+impl From<String> for DomainType {
+    fn from(raw: String) -> Self {
+        if raw == "com.apple.dt.IDE.BuildLogSection" {
+            return DomainType::BuildStep;
+        }
+        if let Some(product_type) = raw.strip_prefix(TARGET_DOMAIN_TYPE_PREFIX) {
+            return DomainType::Target(product_type.to_string());
+        }
+        match raw.strip_prefix(DOMAIN_TYPE_PREFIX) {
+            Some("BuildLog") => DomainType::Build,
+            Some("CleanLog") => DomainType::Clean,
+            Some("TestLog") => DomainType::Test,
+            Some("ArchiveLog") => DomainType::Archive,
+            _ => DomainType::Unknown(raw),
+        }
+    }
+}
+
+impl DomainType {
+    /// The string as it is stored in the log
+    pub fn raw(&self) -> String {
+        match self {
+            DomainType::Build => format!("{DOMAIN_TYPE_PREFIX}BuildLog"),
+            DomainType::Clean => format!("{DOMAIN_TYPE_PREFIX}CleanLog"),
+            DomainType::Test => format!("{DOMAIN_TYPE_PREFIX}TestLog"),
+            DomainType::Archive => format!("{DOMAIN_TYPE_PREFIX}ArchiveLog"),
+            DomainType::Target(product_type) => {
+                format!("{TARGET_DOMAIN_TYPE_PREFIX}{product_type}")
+            }
+            DomainType::BuildStep => "com.apple.dt.IDE.BuildLogSection".to_string(),
+            DomainType::Unknown(raw) => raw.clone(),
+        }
+    }
+}
+
+impl fmt::Display for DomainType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DomainType::Build => write!(f, "build"),
+            DomainType::Clean => write!(f, "clean"),
+            DomainType::Test => write!(f, "test"),
+            DomainType::Archive => write!(f, "archive"),
+            DomainType::Target(product_type) => write!(f, "target:{product_type}"),
+            DomainType::BuildStep => write!(f, "build_step"),
+            DomainType::Unknown(raw) => write!(f, "{raw}"),
+        }
+    }
+}
+
+impl Serialize for DomainType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn maps_raw_section_types() {
+        assert_eq!(SectionType::from(0), SectionType::Main);
+        assert_eq!(SectionType::from(1), SectionType::Target);
+        assert_eq!(SectionType::from(2), SectionType::Command);
+        assert_eq!(SectionType::from(7), SectionType::Unknown(7));
+        for raw in [-1, 0, 1, 2, 3, 7] {
+            assert_eq!(SectionType::from(raw).raw(), raw);
+        }
+    }
+
+    #[test]
+    fn maps_raw_severities() {
+        assert_eq!(Severity::from(0), Severity::Note);
+        assert_eq!(Severity::from(1), Severity::Warning);
+        assert_eq!(Severity::from(2), Severity::Error);
+        assert_eq!(Severity::from(3), Severity::Analyzer);
+        assert_eq!(Severity::from(7), Severity::Unknown(7));
+        for raw in [-1, 0, 1, 2, 3, 7] {
+            assert_eq!(Severity::from(raw).raw(), raw);
+        }
+    }
+
+    #[test]
+    fn maps_raw_domain_types() {
+        let raws = [
+            ("Xcode.IDEActivityLogDomainType.BuildLog", DomainType::Build),
+            ("Xcode.IDEActivityLogDomainType.CleanLog", DomainType::Clean),
+            ("Xcode.IDEActivityLogDomainType.TestLog", DomainType::Test),
+            (
+                "Xcode.IDEActivityLogDomainType.ArchiveLog",
+                DomainType::Archive,
+            ),
+            (
+                "Xcode.IDEActivityLogDomainType.target.product-type.framework",
+                DomainType::Target("framework".to_string()),
+            ),
+            ("com.apple.dt.IDE.BuildLogSection", DomainType::BuildStep),
+            (
+                "Xcode.IDEActivityLogDomainType.XCBuild.Preparation",
+                DomainType::Unknown(
+                    "Xcode.IDEActivityLogDomainType.XCBuild.Preparation".to_string(),
+                ),
+            ),
+        ];
+        for (raw, domain_type) in raws {
+            assert_eq!(DomainType::from(raw.to_string()), domain_type);
+            assert_eq!(domain_type.raw(), raw);
+        }
+    }
+
+    #[test]
+    fn serializes_unknown_values_as_strings() {
+        assert_eq!(
+            serde_json::to_value([SectionType::Main, SectionType::Unknown(7)]).unwrap(),
+            json!(["main", "unknown(7)"])
+        );
+        assert_eq!(
+            serde_json::to_value([Severity::Error, Severity::Unknown(-1)]).unwrap(),
+            json!(["error", "unknown(-1)"])
+        );
+        assert_eq!(
+            serde_json::to_value([
+                DomainType::Target("application".to_string()),
+                DomainType::Unknown("com.example.Log".to_string()),
+            ])
+            .unwrap(),
+            json!(["target:application", "com.example.Log"])
+        );
+    }
+}
@@ -3,24 +3,269 @@
 /// https://github.com/MobileNativeFoundation/XCLogParser/blob/master/docs/Xcactivitylog%20Format.md
 mod cli;
 
-use swift_tool_box::{deser, export, parser, read_gzipped_file};
+use serde::Serialize;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use swift_tool_box::markdown::{Markdown, ToMarkdown};
+use swift_tool_box::{
+    cache, compile_db, critical_path, deser, diff, export, flag_audit, function_bodies, log_store,
+    parser, read_gzipped_file, read_log, redact, render, resources, select, summary, tree,
+    type_check, why_failed,
+};
 
 fn main() {
     env_logger::init();
 
     let args = <cli::Args as clap::Parser>::parse();
 
-    let contents = read_gzipped_file(&args.input).unwrap();
+    match args.command {
+        Some(cli::Command::Diff(diff_args)) => {
+            let base = read_log(&resolve_log(&diff_args.base).unwrap()).unwrap();
+            let head = read_log(&resolve_log(&diff_args.head).unwrap()).unwrap();
+            print_plain_report(&diff::diff(&base, &head), diff_args.format).unwrap();
+        }
+        Some(cli::Command::Cache(report_args)) => {
+            let log = read_log(&resolve_log(&report_args.input).unwrap()).unwrap();
+            print_report(&cache::cache_report(&log), report_args.format).unwrap();
+        }
+        Some(cli::Command::TypeCheck(report_args)) => {
+            let log = read_log(&resolve_log(&report_args.input).unwrap()).unwrap();
+            print_report(&type_check::type_check_report(&log), report_args.format).unwrap();
+        }
+        Some(cli::Command::FunctionBodies(report_args)) => {
+            let log = read_log(&resolve_log(&report_args.input).unwrap()).unwrap();
+            let report = function_bodies::function_bodies_report(&log);
+            print_plain_report(&report, report_args.format).unwrap();
+        }
+        Some(cli::Command::Tree(tree_args)) => {
+            let log = read_log(&resolve_log(&tree_args.input).unwrap()).unwrap();
+            let options = tree::TreeOptions {
+                max_depth: tree_args.depth,
+                min_duration: tree_args.min_duration,
+                title_filter: tree_args.filter,
+            };
+            print_plain_report(&tree::section_tree(&log, &options), tree_args.format).unwrap();
+        }
+        Some(cli::Command::Render(render_args)) => {
+            let log = read_log(&resolve_log(&render_args.input).unwrap()).unwrap();
+            render_log(&log, &render_args).unwrap();
+        }
+        Some(cli::Command::WhyFailed(report_args)) => {
+            let log = read_log(&resolve_log(&report_args.input).unwrap()).unwrap();
+            print_plain_report(&why_failed::failure_report(&log), report_args.format).unwrap();
+        }
+        Some(cli::Command::Summary(report_args)) => {
+            let log = read_log(&resolve_log(&report_args.input).unwrap()).unwrap();
+            print_report(&summary::build_summary(&log), report_args.format).unwrap();
+        }
+        Some(cli::Command::CriticalPath(report_args)) => {
+            let log = read_log(&resolve_log(&report_args.input).unwrap()).unwrap();
+            let report = critical_path::critical_path_report(&log);
+            print_report(&report, report_args.format).unwrap();
+        }
+        Some(cli::Command::Resources(report_args)) => {
+            let log = read_log(&resolve_log(&report_args.input).unwrap()).unwrap();
+            print_report(&resources::resource_report(&log), report_args.format).unwrap();
+        }
+        Some(cli::Command::AuditFlags(report_args)) => {
+            let log = read_log(&resolve_log(&report_args.input).unwrap()).unwrap();
+            print_plain_report(&flag_audit::flag_audit_report(&log), report_args.format).unwrap();
+        }
+        Some(cli::Command::CompileCommands(compile_args)) => {
+            let log = read_log(&resolve_log(&compile_args.input).unwrap()).unwrap();
+            let commands = compile_db::compile_commands(&log);
+            log::info!("Found {} compile commands", commands.len());
+            compile_db::write_compile_commands(&commands, &compile_args.output).unwrap();
+        }
+        None => {
+            if args.select.is_some() && matches!(args.output, Some(cli::OutputFile::Csv(_))) {
+                <cli::Args as clap::CommandFactory>::command()
+                    .error(
+                        clap::error::ErrorKind::ArgumentConflict,
+                        "--select writes .json or .ndjson output only",
+                    )
+                    .exit();
+            }
+            let mut redactor = (args.redact || !args.redact_pattern.is_empty())
+                .then(|| redact::Redactor::new(args.redact_pattern));
+            convert_logs(
+                &args.input.unwrap(),
+                &args.output.unwrap(),
+                args.all,
+                args.select.as_ref(),
+                redactor.as_mut(),
+            )
+        }
+    }
+}
+
+/// Converts the input log, or the newest/all logs if the input is a logs directory
+fn convert_logs(
+    input: &PathBuf,
+    output: &cli::OutputFile,
+    all: bool,
+    selector: Option<&select::Selector>,
+    mut redactor: Option<&mut redact::Redactor>,
+) {
+    if !input.is_dir() {
+        convert(input, output, selector, redactor).unwrap();
+        return;
+    }
+
+    let store = log_store::LogStore::open(input).unwrap();
+    let entries = match all {
+        true => store.logs.iter().collect::<Vec<_>>(),
+        false => store.newest().into_iter().collect(),
+    };
+    if entries.is_empty() {
+        log::warn!("No logs in {}", store.directory.to_string_lossy());
+    }
+    for entry in entries {
+        log::info!(
+            "Converting {} ({:?}, scheme {:?}, started {})",
+            entry.file_name,
+            entry.title,
+            entry.scheme,
+            entry.time_started_recording
+        );
+        let output = match all {
+            true => output.for_log(&entry.unique_identifier),
+            false => output.clone(),
+        };
+        // One redactor for all logs keeps their pseudonyms consistent
+        convert(&entry.path, &output, selector, redactor.as_deref_mut()).unwrap();
+    }
+}
+
+fn convert(
+    input: &PathBuf,
+    output: &cli::OutputFile,
+    selector: Option<&select::Selector>,
+    mut redactor: Option<&mut redact::Redactor>,
+) -> anyhow::Result<()> {
+    if let Some(selector) = selector {
+        return convert_selected(input, output, selector, redactor);
+    }
+
+    let contents = read_gzipped_file(input)?;
     let mut parser = parser::Parser::new(contents);
 
-    match args.output {
+    let mut redact_object = |mut object| {
+        if let Some(redactor) = redactor.as_deref_mut() {
+            redactor.redact_object(&mut object);
+        }
+        object
+    };
+    match output {
         cli::OutputFile::Json(path) => {
             let mut tokens = parser.iter().peekable();
-            let result = deser::Deserializer::new(&mut tokens);
-            export::to_json(result, &path).unwrap();
+            let result = deser::Deserializer::new(&mut tokens).map(&mut redact_object);
+            export::to_json(result, path)
+        }
+        cli::OutputFile::Ndjson(path) => {
+            let mut tokens = parser.iter().peekable();
+            let result = deser::Deserializer::new(&mut tokens).map(&mut redact_object);
+            export::to_ndjson(result, path)
         }
         cli::OutputFile::Csv(path) => {
-            export::to_csv(parser.iter(), &path).unwrap();
+            let tokens = parser.iter().map(|token| match redactor.as_deref_mut() {
+                Some(redactor) => redactor.redact_token(token),
+                None => token,
+            });
+            export::to_csv(tokens, path)
         }
     }
 }
+
+/// Writes the nodes matching `selector` only. Nodes are selected before redaction,
+/// so that selectors can use the names the log really has.
+fn convert_selected(
+    input: &PathBuf,
+    output: &cli::OutputFile,
+    selector: &select::Selector,
+    redactor: Option<&mut redact::Redactor>,
+) -> anyhow::Result<()> {
+    let contents = read_gzipped_file(input)?;
+    let mut parser = parser::Parser::new(contents);
+    let mut tokens = parser.iter().peekable();
+    let objects = deser::Deserializer::new(&mut tokens).collect();
+    let mut selected = selector.extract(objects);
+
+    if let Some(redactor) = redactor {
+        selected
+            .iter_mut()
+            .for_each(|object| redactor.redact_object(object));
+    }
+    match output {
+        cli::OutputFile::Json(path) => export::to_json(selected, path),
+        cli::OutputFile::Ndjson(path) => export::to_ndjson(selected, path),
+        // Class instances of a node refer to class names defined anywhere before it,
+        // the tokens of a node alone can't be read again
+        cli::OutputFile::Csv(_) => anyhow::bail!("--select writes .json or .ndjson output only"),
+    }
+}
+
+/// A log file as is, or the newest log of a logs directory
+fn resolve_log(input: &Path) -> anyhow::Result<PathBuf> {
+    if !input.is_dir() {
+        return Ok(input.to_path_buf());
+    }
+    let store = log_store::LogStore::open(input)?;
+    match store.newest() {
+        Some(entry) => Ok(entry.path.clone()),
+        None => anyhow::bail!("No logs in {}", store.directory.to_string_lossy()),
+    }
+}
+
+fn render_log(log: &[deser::XActivityLogObject], args: &cli::RenderArgs) -> anyhow::Result<()> {
+    let verbosity = match (args.quiet, args.verbose) {
+        (true, _) => render::Verbosity::Quiet,
+        (false, 0) => render::Verbosity::Normal,
+        (false, 1) => render::Verbosity::Verbose,
+        (false, _) => render::Verbosity::Debug,
+    };
+    let color = match args.color {
+        cli::ColorMode::Auto => anstream::ColorChoice::Auto,
+        cli::ColorMode::Always => anstream::ColorChoice::Always,
+        cli::ColorMode::Never => anstream::ColorChoice::Never,
+    };
+    let mut out = anstream::AutoStream::new(io::stdout().lock(), color);
+    match render::render(log, verbosity, &mut out) {
+        // The reader, e.g. `head`, has seen enough
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+fn print_report<R: Serialize + ToMarkdown>(
+    report: &R,
+    format: cli::ReportFormat,
+) -> anyhow::Result<()> {
+    match format {
+        cli::ReportFormat::Text => print_plain_report(report, cli::PlainReportFormat::Text),
+        cli::ReportFormat::Json => print_plain_report(report, cli::PlainReportFormat::Json),
+        cli::ReportFormat::Markdown => write_stdout(format_args!("{}", Markdown(report))),
+    }
+}
+
+fn print_plain_report<R: Serialize + fmt::Display>(
+    report: &R,
+    format: cli::PlainReportFormat,
+) -> anyhow::Result<()> {
+    match format {
+        cli::PlainReportFormat::Text => write_stdout(format_args!("{report}")),
+        cli::PlainReportFormat::Json => {
+            let json = serde_json::to_string_pretty(report)?;
+            write_stdout(format_args!("{json}\n"))
+        }
+    }
+}
+
+fn write_stdout(text: fmt::Arguments) -> anyhow::Result<()> {
+    match io::stdout().lock().write_fmt(text) {
+        // The reader, e.g. `head`, has seen enough
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
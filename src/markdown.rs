@@ -0,0 +1,28 @@
+use std::fmt;
+
+/// Markdown form of a report, e.g. for CI job summaries
+pub trait ToMarkdown: fmt::Display {
+    fn fmt_markdown(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+/// Displays the wrapped report as Markdown
+pub struct Markdown<'a, R: ?Sized>(pub &'a R);
+
+impl<R: ToMarkdown + ?Sized> fmt::Display for Markdown<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_markdown(f)
+    }
+}
+
+/// Escapes the characters that would break a table cell
+pub fn escape_cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('\n', " ")
+}
+
+/// Writes the header and separator rows of a table
+pub fn write_table_header(f: &mut fmt::Formatter<'_>, columns: &[&str]) -> fmt::Result {
+    writeln!(f, "| {} |", columns.join(" | "))?;
+    writeln!(f, "|{}", "---|".repeat(columns.len()))
+}
@@ -1,7 +1,7 @@
 use anyhow::bail;
-use std::io::{self, BufReader, Read};
+use std::io::{self, BufRead, BufReader, Read};
 
-use crate::token::{Token, TokenType};
+use crate::token::{BorrowedToken, Token, TokenType};
 
 /// Main struct for SLF parsing
 pub struct Parser<T>
@@ -9,6 +9,8 @@ where
     T: Read,
 {
     contents: BufReader<T>,
+    /// Left hand side of the token being scanned, reused between tokens
+    lhs: Vec<u8>,
 }
 
 impl<T> Parser<T>
@@ -18,13 +20,19 @@ where
     pub fn new(contents: T) -> Self {
         Self {
             contents: BufReader::new(contents),
+            lhs: Vec::with_capacity(32),
         }
     }
 
     /// Scans token from the `contents`
     /// Fails on wrong SLF format or EOF
     pub fn scan_token(&mut self) -> anyhow::Result<Token> {
-        let (lhs, token_type) = self.scan_lhs_and_token_type()?;
+        let token_type = self.scan_lhs_and_token_type()?;
+        let lhs = if self.lhs.is_empty() {
+            None
+        } else {
+            Some(self.lhs.as_slice())
+        };
         log::trace!("Left hand side: {:?}, Token type: {:?}", lhs, token_type);
 
         let token = match (token_type, lhs) {
@@ -33,58 +41,41 @@ where
             (token_type, Some(lhs)) => match token_type {
                 // Example: `200#`
                 // Left hand side value: An unsigned 64 bits integer.
-                TokenType::Int => Token::Int(lhs.parse::<u64>()?),
+                TokenType::Int => Token::Int(parse_uint(lhs)?),
 
                 // Example: `afd021ebae48c141^`
                 // Left hand side value: A little-endian floating point number, encoded in hexadecimal.
-                TokenType::Double => {
-                    let bytes = (0..lhs.len())
-                        .step_by(2)
-                        .filter_map(|i| u8::from_str_radix(&lhs[i..i + 2], 16).ok())
-                        .collect::<Vec<_>>();
-                    let arr: [u8; 8] = bytes.try_into().unwrap();
-                    let data = f64::from_le_bytes(arr);
-                    Token::Double(data)
-                }
+                TokenType::Double => Token::Double(parse_hex_double(lhs)?),
 
                 // Example: `21%IDEActivityLogSection`
                 // Left hand side value: An `Integer` with the number of characters that are part of the `Class name`.
                 // Right hand side value: The characters that are part of the `Class name`
                 TokenType::ClassName => {
-                    let size = lhs.parse::<usize>()?;
-                    let mut buf = vec![0; size];
-                    self.contents.read_exact(&mut buf)?;
-                    let data = String::from_utf8(buf)?;
-                    Token::ClassName(data)
+                    let size = parse_uint(lhs)? as usize;
+                    Token::ClassName(self.scan_string(size)?)
                 }
 
                 // TODO: The following comment is wrong, there could be a string too
                 // Example: `2@`
                 // Left hand side value: An `Integer` with the index of the `Class name` of the `Class instance`'s type.
-                TokenType::ClassInstance => Token::ClassInstance(lhs.parse::<usize>()?),
+                TokenType::ClassInstance => Token::ClassInstance(parse_uint(lhs)? as usize),
 
                 // Example: `5"Hello`
                 // Left hand side value: An `Integer` with the number of characters that are part of the `String`.
                 // Right hand side value: The characters that are part of the `String`
                 TokenType::String => {
-                    log::debug!("Lhs: {lhs}");
-                    let size = lhs.parse::<usize>()?;
-                    let mut buf = vec![0; size];
-                    self.contents.read_exact(&mut buf)?;
-                    let data = String::from_utf8(buf)?;
+                    let size = parse_uint(lhs)? as usize;
+                    let data = self.scan_string(size)?;
                     log::trace!("Read string: {:?}", data);
                     Token::String(data)
                 }
 
                 // Example: `22(`
                 // Left hand side value: An `Integer` with the number of elements that are part of the `Array`.
-                TokenType::Array => Token::Array(lhs.parse::<usize>()?),
+                TokenType::Array => Token::Array(parse_uint(lhs)? as usize),
                 TokenType::Json => {
-                    let size = lhs.parse::<usize>()?;
-                    let mut buf = vec![0; size];
-                    self.contents.read_exact(&mut buf)?;
-                    let data = String::from_utf8(buf)?;
-                    Token::Json(data)
+                    let size = parse_uint(lhs)? as usize;
+                    Token::Json(self.scan_string(size)?)
                 }
 
                 TokenType::Null => bail!("Wrong SLF format. Got Null and some lhs"),
@@ -95,24 +86,43 @@ where
         Ok(token)
     }
 
-    /// Scans the left hand side value and determine the token type
-    fn scan_lhs_and_token_type(&mut self) -> anyhow::Result<(Option<String>, TokenType)> {
-        let mut lhs = String::new();
-        let mut buf = [0; 1];
+    /// Scans the left hand side value into `self.lhs` and determine the token type.
+    /// Works on whole buffered chunks, bytes are only copied into `self.lhs`.
+    fn scan_lhs_and_token_type(&mut self) -> anyhow::Result<TokenType> {
+        self.lhs.clear();
 
         loop {
-            self.contents.read_exact(&mut buf)?;
-            let value = buf[0] as char;
-            if let Ok(token_type) = TokenType::try_from(value) {
-                log::trace!("Got {} token type", value);
-                let payload = if lhs.is_empty() { None } else { Some(lhs) };
-                return Ok((payload, token_type));
+            let buf = self.contents.fill_buf()?;
+            if buf.is_empty() {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
             }
 
-            lhs.push(value);
+            let found = buf
+                .iter()
+                .enumerate()
+                .find_map(|(i, &b)| TokenType::from_byte(b).map(|t| (i, t)));
+            match found {
+                Some((i, token_type)) => {
+                    self.lhs.extend_from_slice(&buf[..i]);
+                    self.contents.consume(i + 1);
+                    return Ok(token_type);
+                }
+                None => {
+                    let size = buf.len();
+                    self.lhs.extend_from_slice(buf);
+                    self.contents.consume(size);
+                }
+            }
         }
     }
 
+    /// Reads `size` bytes of UTF-8 text following the token type
+    fn scan_string(&mut self, size: usize) -> anyhow::Result<String> {
+        let mut buf = vec![0; size];
+        self.contents.read_exact(&mut buf)?;
+        Ok(String::from_utf8(buf)?)
+    }
+
     /// Reads `SLF0` header
     fn scan_header(&mut self) -> io::Result<()> {
         let mut buf = [0; 4];
@@ -120,7 +130,7 @@ where
         Ok(())
     }
 
-    pub fn iter(&mut self) -> ParserIterator<T> {
+    pub fn iter(&mut self) -> ParserIterator<'_, T> {
         self.scan_header().unwrap();
         ParserIterator {
             parser: self,
@@ -147,9 +157,219 @@ where
         let token = self.parser.scan_token();
         log::debug!("[{}]: {token:?}", self.token_idx);
         self.token_idx += 1;
-        match token {
-            Ok(t) => Some(t),
-            Err(_) => None,
+        token.ok()
+    }
+}
+
+/// Parses an unsigned decimal integer straight from the left hand side bytes
+fn parse_uint(lhs: &[u8]) -> anyhow::Result<u64> {
+    if lhs.is_empty() {
+        bail!("Expected an integer, got an empty left hand side");
+    }
+    lhs.iter().try_fold(0u64, |acc, &b| {
+        if !b.is_ascii_digit() {
+            bail!("Expected a digit, got {:?}", b as char);
+        }
+        acc.checked_mul(10)
+            .and_then(|acc| acc.checked_add(u64::from(b - b'0')))
+            .ok_or_else(|| anyhow::anyhow!("Integer overflow"))
+    })
+}
+
+/// Decodes a little-endian double encoded as 16 hexadecimal digits
+fn parse_hex_double(lhs: &[u8]) -> anyhow::Result<f64> {
+    fn nibble(b: u8) -> anyhow::Result<u8> {
+        Ok(match b {
+            b'0'..=b'9' => b - b'0',
+            b'a'..=b'f' => b - b'a' + 10,
+            b'A'..=b'F' => b - b'A' + 10,
+            _ => bail!("Expected a hex digit, got {:?}", b as char),
+        })
+    }
+
+    if lhs.len() != 16 {
+        bail!("Expected 16 hex digits for a double, got {}", lhs.len());
+    }
+    let mut bytes = [0u8; 8];
+    for (byte, pair) in bytes.iter_mut().zip(lhs.chunks_exact(2)) {
+        *byte = (nibble(pair[0])? << 4) | nibble(pair[1])?;
+    }
+    Ok(f64::from_le_bytes(bytes))
+}
+
+/// SLF parser over a buffer that is already in memory.
+/// Strings, class names and JSON are borrowed from the buffer, so no allocation
+/// happens per token. Use [`crate::load_log`] to get a buffer from a file.
+pub struct SliceParser<'a> {
+    contents: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SliceParser<'a> {
+    /// Fails if `contents` doesn't start with the `SLF0` header
+    pub fn new(contents: &'a [u8]) -> anyhow::Result<Self> {
+        if !contents.starts_with(b"SLF0") {
+            bail!("Wrong SLF format. Missing SLF0 header");
+        }
+        Ok(Self {
+            contents,
+            position: 4,
+        })
+    }
+
+    /// Scans token from the `contents`
+    /// Fails on wrong SLF format or end of the buffer
+    pub fn scan_token(&mut self) -> anyhow::Result<BorrowedToken<'a>> {
+        let (lhs, token_type) = self.scan_lhs_and_token_type()?;
+
+        let token = match (token_type, lhs) {
+            (TokenType::Null, None) => BorrowedToken::Null,
+            (TokenType::Null, Some(_)) => bail!("Wrong SLF format. Got Null and some lhs"),
+            (TokenType::Int, Some(lhs)) => BorrowedToken::Int(parse_uint(lhs)?),
+            (TokenType::Double, Some(lhs)) => BorrowedToken::Double(parse_hex_double(lhs)?),
+            (TokenType::ClassInstance, Some(lhs)) => {
+                BorrowedToken::ClassInstance(parse_uint(lhs)? as usize)
+            }
+            (TokenType::Array, Some(lhs)) => BorrowedToken::Array(parse_uint(lhs)? as usize),
+            (TokenType::ClassName, Some(lhs)) => {
+                BorrowedToken::ClassName(self.scan_str(parse_uint(lhs)? as usize)?)
+            }
+            (TokenType::String, Some(lhs)) => {
+                BorrowedToken::String(self.scan_str(parse_uint(lhs)? as usize)?)
+            }
+            (TokenType::Json, Some(lhs)) => {
+                BorrowedToken::Json(self.scan_str(parse_uint(lhs)? as usize)?)
+            }
+            _ => bail!("Wrong token type and lhs combo."),
+        };
+
+        Ok(token)
+    }
+
+    /// Scans the left hand side value and determine the token type
+    fn scan_lhs_and_token_type(&mut self) -> anyhow::Result<(Option<&'a [u8]>, TokenType)> {
+        let rest = &self.contents[self.position..];
+        for (i, &b) in rest.iter().enumerate() {
+            if let Some(token_type) = TokenType::from_byte(b) {
+                self.position += i + 1;
+                let lhs = if i == 0 { None } else { Some(&rest[..i]) };
+                return Ok((lhs, token_type));
+            }
+        }
+        bail!("Unexpected end of SLF buffer")
+    }
+
+    /// Borrows `size` bytes of UTF-8 text following the token type
+    fn scan_str(&mut self, size: usize) -> anyhow::Result<&'a str> {
+        let end = match self.position.checked_add(size) {
+            Some(end) if end <= self.contents.len() => end,
+            _ => bail!("Unexpected end of SLF buffer"),
+        };
+        let data = std::str::from_utf8(&self.contents[self.position..end])?;
+        self.position = end;
+        Ok(data)
+    }
+}
+
+impl<'a> Iterator for SliceParser<'a> {
+    type Item = BorrowedToken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.contents.len() {
+            return None;
         }
+        match self.scan_token() {
+            Ok(token) => Some(token),
+            Err(e) => {
+                log::warn!("Stopped scanning at byte {}: {e}", self.position);
+                self.position = self.contents.len();
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex_double(value: f64) -> String {
+        value
+            .to_le_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// Debug output of the tokens of both parsers, `Token` has no `PartialEq`
+    fn tokens(contents: &[u8]) -> (Vec<String>, Vec<String>) {
+        let owned = Parser::new(contents)
+            .iter()
+            .map(|t| format!("{t:?}"))
+            .collect();
+        let borrowed = SliceParser::new(contents)
+            .unwrap()
+            .map(|t| format!("{:?}", Token::from(t)))
+            .collect();
+        (owned, borrowed)
+    }
+
+    #[test]
+    fn parses_unsigned_integers() {
+        assert_eq!(parse_uint(b"0").unwrap(), 0);
+        assert_eq!(parse_uint(b"200").unwrap(), 200);
+        assert_eq!(parse_uint(b"18446744073709551615").unwrap(), u64::MAX);
+        assert!(parse_uint(b"18446744073709551616").is_err());
+        assert!(parse_uint(b"").is_err());
+        assert!(parse_uint(b"12a").is_err());
+        assert!(parse_uint(b"-1").is_err());
+    }
+
+    #[test]
+    fn parses_hex_doubles() {
+        assert_eq!(parse_hex_double(b"0000000000000000").unwrap(), 0.0);
+        // Little-endian, as written by Xcode
+        assert_eq!(parse_hex_double(b"000000000000f83f").unwrap(), 1.5);
+        assert_eq!(parse_hex_double(b"000000000000F83F").unwrap(), 1.5);
+        let value = 623_149_234.123;
+        assert_eq!(
+            parse_hex_double(hex_double(value).as_bytes()).unwrap(),
+            value
+        );
+        assert!(parse_hex_double(b"000000000000f8").is_err());
+        assert!(parse_hex_double(b"000000000000f83f00").is_err());
+        assert!(parse_hex_double(b"000000000000f8zz").is_err());
+        assert!(parse_hex_double(b"").is_err());
+    }
+
+    #[test]
+    fn slice_parser_matches_parser() {
+        let json = r#"{"wcStartTime":1}"#;
+        let contents = format!(
+            "SLF010#21%IDEActivityLogSection1@{}^-{}^5\"Hello0\"-3\"é!2({}*",
+            hex_double(1.5),
+            hex_double(-0.25),
+            json.len()
+        ) + json;
+        let (owned, borrowed) = tokens(contents.as_bytes());
+        assert_eq!(owned.len(), 12);
+        assert_eq!(owned, borrowed);
+        assert_eq!(owned[1], r#"ClassName("IDEActivityLogSection")"#);
+        assert_eq!(owned[3], "Double(1.5)");
+        assert_eq!(owned[5], "Double(-0.25)");
+        assert_eq!(owned[9], r#"String("é!")"#);
+        assert_eq!(owned[11], format!("Json({json:?})"));
+    }
+
+    #[test]
+    fn parsers_stop_at_truncated_buffer() {
+        let (owned, borrowed) = tokens(b"SLF01#2(10\"short");
+        assert_eq!(owned, ["Int(1)", "Array(2)"]);
+        assert_eq!(owned, borrowed);
+
+        // A corrupt length must not overflow the position
+        let contents = format!("SLF0{}\"abc", u64::MAX);
+        let mut parser = SliceParser::new(contents.as_bytes()).unwrap();
+        assert!(parser.scan_token().is_err());
     }
 }
@@ -0,0 +1,393 @@
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::deser::XActivityLogObject;
+use crate::log_class::{
+    DVTDocumentLocation, IDEActivityLogCommandInvocationSection, IDEActivityLogMessage,
+    IDEActivityLogSection, IDEActivityLogUnitTestSection, IDECommandLineBuildLog,
+};
+use crate::token::Token;
+
+/// Built-in rules: name of the pseudonyms and the pattern. The first capture group
+/// that matched is replaced, or the whole match if the pattern has no groups.
+const BUILT_IN_RULES: &[(&str, &str)] = &[
+    ("user", r"/(?:Users|home)/([^/\s'\x22\\]+)"),
+    ("user", r"\b(?:USER|LOGNAME)\\?=([^\s/'\x22]+)"),
+    // `DerivedData/MyApp-<28 letters>`, the letters are a hash of the project path
+    ("hash", r"DerivedData/[^/\s]+-([a-z]{28})\b"),
+    (
+        "uuid",
+        r"\b[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12}\b",
+    ),
+    // Environment variables with credentials, e.g. `export GITHUB_TOKEN\=...`
+    (
+        "secret",
+        r"\b(?:[A-Za-z_][A-Za-z0-9_]*)?(?i:token|secret|password|passwd|api_?key|access_?key|auth)[A-Za-z0-9_]*\\?=('[^']*'|\x22[^\x22]*\x22|[^\s'\x22]+)",
+    ),
+    ("host", r"\b([A-Za-z0-9][A-Za-z0-9-]*)\.local\b"),
+    ("host", r"\bHOSTNAME\\?=(\S+)"),
+];
+
+/// Matches that are never replaced, `/Users/Shared` belongs to no one
+const KEPT_VALUES: &[&str] = &["Shared"];
+
+struct Rule {
+    pseudonym: &'static str,
+    regex: Regex,
+}
+
+/// Replaces personal and secret values of a log with pseudonyms like `user1` or `secret2`.
+/// The same value always gets the same pseudonym, numbered in order of first appearance,
+/// so logs redacted by one `Redactor` can still be compared with each other.
+pub struct Redactor {
+    rules: Vec<Rule>,
+    pseudonyms: Pseudonyms,
+}
+
+#[derive(Default)]
+struct Pseudonyms {
+    /// Pseudonym of every (rule pseudonym, original value)
+    known: HashMap<(&'static str, String), String>,
+    counters: HashMap<&'static str, usize>,
+}
+
+impl Pseudonyms {
+    fn get(&mut self, kind: &'static str, original: &str) -> String {
+        if let Some(pseudonym) = self.known.get(&(kind, original.to_string())) {
+            return pseudonym.clone();
+        }
+        let counter = self.counters.entry(kind).or_default();
+        *counter += 1;
+        let pseudonym = match kind {
+            // Keep the shape so that parsers of the output still see a UUID
+            "uuid" => format!("00000000-0000-0000-0000-{:012}", counter),
+            kind => format!("{kind}{counter}"),
+        };
+        self.known
+            .insert((kind, original.to_string()), pseudonym.clone());
+        pseudonym
+    }
+}
+
+impl Redactor {
+    /// Redactor with the built-in rules followed by `patterns`, whose matches become `redacted<N>`
+    pub fn new(patterns: Vec<Regex>) -> Self {
+        let built_in = BUILT_IN_RULES.iter().map(|(pseudonym, pattern)| Rule {
+            pseudonym,
+            regex: Regex::new(pattern).expect("built-in redaction rules are valid"),
+        });
+        let custom = patterns.into_iter().map(|regex| Rule {
+            pseudonym: "redacted",
+            regex,
+        });
+        Redactor {
+            rules: built_in.chain(custom).collect(),
+            pseudonyms: Pseudonyms::default(),
+        }
+    }
+
+    pub fn redact_str(&mut self, text: &str) -> String {
+        let mut text = Cow::Borrowed(text);
+        for rule in self.rules.iter() {
+            let replaced = rule.regex.replace_all(&text, |captures: &Captures| {
+                let whole = captures.get(0).unwrap();
+                let Some(group) = captures.iter().skip(1).flatten().next() else {
+                    return self.pseudonyms.get(rule.pseudonym, whole.as_str());
+                };
+                if KEPT_VALUES.contains(&group.as_str()) {
+                    return whole.as_str().to_string();
+                }
+                let pseudonym = self.pseudonyms.get(rule.pseudonym, group.as_str());
+                let start = group.start() - whole.start();
+                let end = group.end() - whole.start();
+                format!(
+                    "{}{pseudonym}{}",
+                    &whole.as_str()[..start],
+                    &whole.as_str()[end..]
+                )
+            });
+            if let Cow::Owned(replaced) = replaced {
+                text = Cow::Owned(replaced);
+            }
+        }
+        text.into_owned()
+    }
+
+    fn redact_string(&mut self, text: &mut String) {
+        *text = self.redact_str(text);
+    }
+
+    fn redact_option(&mut self, text: &mut Option<String>) {
+        if let Some(text) = text {
+            self.redact_string(text);
+        }
+    }
+
+    fn redact_json(&mut self, value: &mut Value) {
+        match value {
+            Value::String(text) => self.redact_string(text),
+            Value::Array(values) => values.iter_mut().for_each(|v| self.redact_json(v)),
+            Value::Object(map) => map.values_mut().for_each(|v| self.redact_json(v)),
+            _ => {}
+        }
+    }
+
+    /// Redacts a raw token of the log, e.g. before writing the tokens out again
+    pub fn redact_token(&mut self, token: Token) -> Token {
+        match token {
+            Token::String(text) => Token::String(self.redact_str(&text)),
+            // Redacting the parsed strings keeps the quotes and braces of the JSON intact
+            Token::Json(text) => match serde_json::from_str::<Value>(&text) {
+                Ok(mut value) => {
+                    self.redact_json(&mut value);
+                    Token::Json(value.to_string())
+                }
+                Err(_) => Token::Json(self.redact_str(&text)),
+            },
+            token => token,
+        }
+    }
+
+    pub fn redact_object(&mut self, object: &mut XActivityLogObject) {
+        match object {
+            XActivityLogObject::IDECommandLineBuildLog(log) => self.redact_log(log),
+            XActivityLogObject::IDEActivityLogSection(section) => self.redact_section(section),
+            XActivityLogObject::IDEActivityLogMessage(message) => self.redact_message(message),
+            XActivityLogObject::IDEActivityLogSectionAttachment(attachment) => {
+                self.redact_json(&mut attachment.unknown1)
+            }
+            XActivityLogObject::IDEActivityLogUnitTestSection(section) => {
+                self.redact_unit_test_section(section)
+            }
+            XActivityLogObject::DVTDocumentLocation(location) => self.redact_location(location),
+            XActivityLogObject::IDEActivityLogCommandInvocationSection(section) => {
+                self.redact_command_invocation_section(section)
+            }
+        }
+    }
+
+    fn redact_log(&mut self, log: &mut IDECommandLineBuildLog) {
+        self.redact_string(&mut log.title);
+        self.redact_string(&mut log.signature);
+        for section in log.sub_sections.iter_mut() {
+            self.redact_section(section);
+        }
+    }
+
+    /// Redacts the section and everything nested in it. Message ranges keep
+    /// pointing into the original text, `section_text` of the messages is redacted as well.
+    pub fn redact_section(&mut self, section: &mut IDEActivityLogSection) {
+        self.redact_string(&mut section.title);
+        self.redact_string(&mut section.signature);
+        self.redact_option(&mut section.text);
+        self.redact_option(&mut section.subtitle);
+        self.redact_option(&mut section.command_details_spect);
+        self.redact_option(&mut section.unique_identifier);
+        self.redact_option(&mut section.localized_result_string);
+        self.redact_option(&mut section.xcbuild_signature);
+        if let Some(location) = &mut section.location {
+            self.redact_location(location);
+        }
+        for attachment in section.attachments.iter_mut() {
+            self.redact_json(&mut attachment.unknown1);
+        }
+        for message in section.messages.iter_mut() {
+            self.redact_message(message);
+        }
+        for sub_section in section.sub_sections.iter_mut() {
+            self.redact_section(sub_section);
+        }
+    }
+
+    fn redact_command_invocation_section(
+        &mut self,
+        section: &mut IDEActivityLogCommandInvocationSection,
+    ) {
+        self.redact_string(&mut section.title);
+        self.redact_string(&mut section.signature);
+        self.redact_option(&mut section.text);
+        for message in section.messages.iter_mut() {
+            self.redact_message(message);
+        }
+        for sub_section in section.sub_sections.iter_mut() {
+            self.redact_section(sub_section);
+        }
+    }
+
+    fn redact_unit_test_section(&mut self, section: &mut IDEActivityLogUnitTestSection) {
+        self.redact_string(&mut section.tests_passed_string);
+        self.redact_string(&mut section.duration_string);
+        self.redact_string(&mut section.summary_string);
+        self.redact_string(&mut section.suite_name);
+        self.redact_string(&mut section.test_name);
+        self.redact_string(&mut section.performance_test_output_string);
+    }
+
+    fn redact_message(&mut self, message: &mut IDEActivityLogMessage) {
+        self.redact_string(&mut message.title);
+        self.redact_option(&mut message.short_title);
+        self.redact_option(&mut message.r#type);
+        self.redact_option(&mut message.category_ident);
+        self.redact_option(&mut message.additional_description);
+        self.redact_option(&mut message.section_text);
+        if let Some(location) = &mut message.location {
+            self.redact_location(location);
+        }
+        for location in message.secondary_locations.iter_mut() {
+            self.redact_location(location);
+        }
+        for sub_message in message.sub_messages.iter_mut() {
+            self.redact_message(sub_message);
+        }
+    }
+
+    fn redact_location(&mut self, location: &mut DVTDocumentLocation) {
+        match location {
+            DVTDocumentLocation::Base(base) => self.redact_string(&mut base.document_url_string),
+            DVTDocumentLocation::Text(text) => {
+                self.redact_string(&mut text.base.document_url_string)
+            }
+            DVTDocumentLocation::Member(member) => {
+                self.redact_string(&mut member.base.document_url_string);
+                self.redact_string(&mut member.member);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact(text: &str) -> String {
+        Redactor::new(vec![]).redact_str(text)
+    }
+
+    #[test]
+    fn redacts_home_directories() {
+        let mut redactor = Redactor::new(vec![]);
+        assert_eq!(
+            redactor.redact_str("cd /Users/jdoe/Developer/MyApp && ls /home/ci/cache"),
+            "cd /Users/user1/Developer/MyApp && ls /home/user2/cache"
+        );
+        assert_eq!(
+            redactor.redact_str("-I/Users/jdoe/Headers /Users/Shared/Frameworks"),
+            "-I/Users/user1/Headers /Users/Shared/Frameworks"
+        );
+    }
+
+    #[test]
+    fn redacts_credentials_in_environment() {
+        let cases = [
+            (
+                "export GITHUB_TOKEN\\=ghp_abc123",
+                "export GITHUB_TOKEN\\=secret1",
+            ),
+            (
+                "export GITHUB_TOKEN=ghp_abc123",
+                "export GITHUB_TOKEN=secret1",
+            ),
+            (
+                "export GITHUB_TOKEN\\=\"ghp abc123\" && next",
+                "export GITHUB_TOKEN\\=secret1 && next",
+            ),
+            ("export API_KEY='abc 123'", "export API_KEY=secret1"),
+            (
+                "export DB_PASSWORD\\=hunter2",
+                "export DB_PASSWORD\\=secret1",
+            ),
+            ("export TOKEN=abc", "export TOKEN=secret1"),
+            ("\"GITHUB_TOKEN=abc\"", "\"GITHUB_TOKEN=secret1\""),
+            ("env 'API_KEY=abc' next", "env 'API_KEY=secret1' next"),
+            // Not a credential
+            ("export LANG\\=en_US.UTF-8", "export LANG\\=en_US.UTF-8"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(redact(text), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn redacts_user_and_host_names() {
+        let mut redactor = Redactor::new(vec![]);
+        assert_eq!(
+            redactor.redact_str("export USER\\=jdoe\nexport HOSTNAME\\=jdoes-mbp"),
+            "export USER\\=user1\nexport HOSTNAME\\=host1"
+        );
+        // Same user as `USER`
+        assert_eq!(redactor.redact_str("/Users/jdoe"), "/Users/user1");
+        assert_eq!(redactor.redact_str("LOGNAME=ci"), "LOGNAME=user2");
+        assert_eq!(
+            redactor.redact_str("ping build-42.local"),
+            "ping host2.local"
+        );
+    }
+
+    #[test]
+    fn redacts_hashes_and_uuids() {
+        let mut redactor = Redactor::new(vec![]);
+        assert_eq!(
+            redactor.redact_str("/tmp/DerivedData/MyApp-abcdefghijklmnopqrstuvwxyzab/Build"),
+            "/tmp/DerivedData/MyApp-hash1/Build"
+        );
+        assert_eq!(
+            redactor.redact_str("Logs/Build/8C2E19C1-4D2A-4B9B-9E0D-6A5F1C3B2A10.xcactivitylog"),
+            "Logs/Build/00000000-0000-0000-0000-000000000001.xcactivitylog"
+        );
+        // Not 28 letters
+        assert_eq!(
+            redactor.redact_str("DerivedData/MyApp-abc/Build"),
+            "DerivedData/MyApp-abc/Build"
+        );
+    }
+
+    #[test]
+    fn keeps_pseudonyms_across_logs() {
+        let mut redactor = Redactor::new(vec![]);
+        let mut first =
+            IDEActivityLogSection::test_step("Build MyApp", "", Some("cd /Users/jdoe/MyApp"));
+        let mut second = IDEActivityLogSection::test_step(
+            "Build MyApp",
+            "",
+            Some("cd /Users/alice/MyApp && cd /Users/jdoe/MyApp"),
+        );
+        redactor.redact_section(&mut first);
+        redactor.redact_section(&mut second);
+        assert_eq!(first.text.as_deref(), Some("cd /Users/user1/MyApp"));
+        assert_eq!(
+            second.text.as_deref(),
+            Some("cd /Users/user2/MyApp && cd /Users/user1/MyApp")
+        );
+    }
+
+    #[test]
+    fn applies_custom_patterns() {
+        let patterns = vec![
+            Regex::new(r"ACME-\d+").unwrap(),
+            Regex::new(r"team ([A-Z0-9]{10})").unwrap(),
+        ];
+        let mut redactor = Redactor::new(patterns);
+        assert_eq!(
+            redactor.redact_str("ACME-123 signed by team ABCDE12345, ACME-123"),
+            "redacted1 signed by team redacted2, redacted1"
+        );
+    }
+
+    #[test]
+    fn keeps_json_tokens_valid() {
+        let mut redactor = Redactor::new(vec![]);
+        let token = redactor.redact_token(Token::Json(
+            r#"{"env":"GITHUB_TOKEN=abc","cwd":"/Users/jdoe/MyApp"}"#.to_string(),
+        ));
+        let Token::Json(text) = token else {
+            panic!("Expected a JSON token, got {token:?}");
+        };
+        let value: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"env": "GITHUB_TOKEN=secret1", "cwd": "/Users/user1/MyApp"})
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use anstyle::{AnsiColor, Style};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::deser::XActivityLogObject;
+use crate::log_class::{IDEActivityLogMessage, IDEActivityLogSection};
+use crate::step_kind::StepKind;
+use crate::walk::{
+    root_only_messages, section_messages, target_section_name, walk_sections, SectionNode,
+};
+
+const ERROR: Style = AnsiColor::Red.on_default().bold();
+const WARNING: Style = AnsiColor::Yellow.on_default();
+const SUCCESS: Style = AnsiColor::Green.on_default();
+const HEADER: Style = Style::new().bold();
+const DIMMED: Style = Style::new().dimmed();
+
+/// How much of the log `render` prints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Errors and the result of the build
+    Quiet,
+    /// Targets, warnings, errors and failed tests
+    Normal,
+    /// Every step and test
+    Verbose,
+    /// Notes and the output of steps with errors as well
+    Debug,
+}
+
+/// Outcome of a test case section titled like
+/// `Test Case '-[MyAppTests.MyTests testFoo]' passed (0.001 seconds).`
+fn test_result(section: &IDEActivityLogSection) -> Option<(&str, bool)> {
+    let rest = section
+        .title
+        .strip_prefix("Test Case '")
+        .or_else(|| section.title.strip_prefix("Test case '"))?;
+    let (name, outcome) = rest.split_once("' ")?;
+    if outcome.starts_with("passed") {
+        Some((name, true))
+    } else if outcome.starts_with("failed") {
+        Some((name, false))
+    } else {
+        None
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+}
+
+/// Short description of a step in the style of xcpretty, e.g. `Compiling File.swift`
+fn describe_step(section: &IDEActivityLogSection) -> String {
+    let info = section.step_info();
+    let source = info.source_file.as_deref().map(file_name);
+    let output = info.output.as_deref().map(file_name);
+    match (&info.kind, source, output) {
+        (StepKind::SwiftCompile | StepKind::CompileC, Some(source), _) => {
+            format!("Compiling {source}")
+        }
+        (StepKind::Ld | StepKind::Libtool, _, Some(output)) => format!("Linking {output}"),
+        (StepKind::CodeSign, _, Some(output)) => format!("Signing {output}"),
+        (StepKind::GenerateDSYMFile, _, Some(output)) => format!("Generating {output}"),
+        (StepKind::CompileAssetCatalog, _, Some(output)) => {
+            format!("Compiling asset catalog {output}")
+        }
+        (StepKind::CompileStoryboard | StepKind::CompileXIB, Some(source), _) => {
+            format!("Compiling {source}")
+        }
+        (StepKind::ProcessInfoPlistFile, Some(source), _) => format!("Processing {source}"),
+        (StepKind::Copy | StepKind::CpResource, Some(source), _) => format!("Copying {source}"),
+        _ => section.title.clone(),
+    }
+}
+
+fn write_message(
+    out: &mut impl Write,
+    indent: &str,
+    message: &IDEActivityLogMessage,
+    verbosity: Verbosity,
+) -> io::Result<()> {
+    let (style, symbol) = if message.is_error() {
+        (ERROR, "✖")
+    } else if message.is_warning() {
+        (WARNING, "⚠")
+    } else if verbosity >= Verbosity::Debug {
+        (DIMMED, "ℹ")
+    } else {
+        return Ok(());
+    };
+    if verbosity == Verbosity::Quiet && !message.is_error() {
+        return Ok(());
+    }
+
+    write!(out, "{indent}{style}{symbol} ")?;
+    if let Some(location) = &message.location {
+        let path = location.file_path();
+        match location.line_and_column() {
+            Some((line, column)) if !path.is_empty() => write!(out, "{path}:{line}:{column}: ")?,
+            _ if !path.is_empty() => write!(out, "{path}: ")?,
+            _ => {}
+        }
+    }
+    writeln!(out, "{}{style:#}", message.title)
+}
+
+fn write_section(
+    out: &mut impl Write,
+    node: &SectionNode,
+    messages: &[&IDEActivityLogMessage],
+    verbosity: Verbosity,
+) -> io::Result<()> {
+    let section = node.section;
+    let indent = "  ".repeat(node.depth());
+    let duration = section.duration().as_seconds_f64();
+    let has_errors = messages.iter().any(|m| m.is_error());
+    let message_indent = format!("{indent}  ");
+
+    if node.depth() == 0 {
+        if verbosity > Verbosity::Quiet {
+            writeln!(out, "{HEADER}▸ {}{HEADER:#}", section.title)?;
+        }
+        for message in messages {
+            write_message(out, &message_indent, message, verbosity)?;
+        }
+        return Ok(());
+    }
+    if let Some(target) = target_section_name(section) {
+        if verbosity > Verbosity::Quiet {
+            writeln!(
+                out,
+                "{indent}{HEADER}▸ {target}{HEADER:#} {DIMMED}{duration:.2}s{DIMMED:#}"
+            )?;
+        }
+    } else if let Some((name, passed)) = test_result(section) {
+        match (passed, verbosity) {
+            (true, Verbosity::Verbose | Verbosity::Debug) => writeln!(
+                out,
+                "{indent}{SUCCESS}✓{SUCCESS:#} {name} {DIMMED}{duration:.3}s{DIMMED:#}"
+            )?,
+            (false, _) => writeln!(
+                out,
+                "{indent}{ERROR}✖ {name}{ERROR:#} {DIMMED}{duration:.3}s{DIMMED:#}"
+            )?,
+            _ => {}
+        }
+    } else if verbosity >= Verbosity::Verbose {
+        let cached = match section.was_fetched_from_cache {
+            true => " (cached)",
+            false => "",
+        };
+        writeln!(
+            out,
+            "{indent}{} {DIMMED}{duration:.2}s{cached}{DIMMED:#}",
+            describe_step(section)
+        )?;
+    }
+
+    for message in messages {
+        write_message(out, &message_indent, message, verbosity)?;
+    }
+
+    if has_errors && verbosity >= Verbosity::Debug {
+        if let Some(text) = &section.text {
+            for line in text.lines() {
+                writeln!(out, "{message_indent}{DIMMED}| {line}{DIMMED:#}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints a condensed transcript of the build: targets, steps, issues and test results.
+/// Colors are written as ANSI escapes, wrap `out` in an `anstream::AutoStream`
+/// to strip them when the output is not a terminal.
+pub fn render(
+    objects: &[XActivityLogObject],
+    verbosity: Verbosity,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    // Messages of the build repeat the messages of its steps, only the ones
+    // it reports itself are shown and counted
+    let root_messages = root_only_messages(objects);
+    let mut warnings = 0;
+    let mut errors = 0;
+    let mut duration = 0.0;
+    for node in walk_sections(objects) {
+        let messages = if node.depth() == 0 {
+            duration += node.section.duration().as_seconds_f64();
+            root_messages
+                .iter()
+                .filter(|(root, _)| std::ptr::eq(root.section, node.section))
+                .map(|(_, message)| *message)
+                .collect()
+        } else {
+            section_messages(node.section)
+        };
+        for message in &messages {
+            warnings += message.is_warning() as usize;
+            errors += message.is_error() as usize;
+        }
+        write_section(out, &node, &messages, verbosity)?;
+    }
+
+    let (style, result) = match errors {
+        0 => (SUCCESS, "Build succeeded"),
+        _ => (ERROR, "Build failed"),
+    };
+    writeln!(
+        out,
+        "\n{style}{result}{style:#} in {duration:.2}s: {warnings} warnings, {errors} errors"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_types::Severity;
+
+    fn render_to_string(objects: &[XActivityLogObject], verbosity: Verbosity) -> String {
+        let mut out = anstream::StripStream::new(Vec::new());
+        render(objects, verbosity, &mut out).unwrap();
+        String::from_utf8(out.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn reports_errors_of_the_build_once() {
+        let mut step = IDEActivityLogSection::test_step(
+            "Compile App.swift",
+            "SwiftCompile normal arm64 /src/App.swift (in target 'App' from project 'App')",
+            None,
+        );
+        let copied =
+            || IDEActivityLogMessage::test_message("cannot find 'x' in scope", Severity::Error);
+        step.messages = vec![copied()];
+        let mut build = IDEActivityLogSection::test_step("Build App", "", None);
+        build.sub_sections = vec![step];
+        build.messages = vec![
+            copied(),
+            IDEActivityLogMessage::test_message(
+                "No profiles for 'com.example.App'",
+                Severity::Error,
+            ),
+        ];
+
+        let output = render_to_string(
+            &[XActivityLogObject::IDEActivityLogSection(build)],
+            Verbosity::Normal,
+        );
+        assert_eq!(output.matches("cannot find 'x' in scope").count(), 1);
+        assert!(output.contains("✖ No profiles for 'com.example.App'"));
+        assert!(output.contains("Build failed in 1.00s: 0 warnings, 2 errors"));
+    }
+
+    #[test]
+    fn counts_errors_only_reported_by_the_build() {
+        let mut build = IDEActivityLogSection::test_step("Build App", "", None);
+        build.messages = vec![IDEActivityLogMessage::test_message(
+            "Scheme App is not currently configured for the build action",
+            Severity::Error,
+        )];
+
+        let output = render_to_string(
+            &[XActivityLogObject::IDEActivityLogSection(build)],
+            Verbosity::Quiet,
+        );
+        assert!(output.contains("✖ Scheme App is not currently configured"));
+        assert!(output.contains("Build failed in 1.00s: 0 warnings, 1 errors"));
+    }
+}
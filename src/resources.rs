@@ -0,0 +1,418 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::attachment::format_bytes;
+use crate::deser::XActivityLogObject;
+use crate::markdown::{escape_cell, write_table_header, ToMarkdown};
+use crate::step_kind::StepKind;
+use crate::walk::walk_sections;
+
+/// Number of rows per table in the text form of the report
+const TEXT_REPORT_LIMIT: usize = 20;
+
+/// A step that used at least this much memory...
+const SWAPPING_MIN_RSS: u64 = 2 * 1024 * 1024 * 1024;
+
+/// ...while getting less than this share of a CPU was likely waiting for memory pages
+const SWAPPING_MAX_CPU_RATIO: f64 = 0.5;
+
+/// Shorter steps are too noisy to tell
+const SWAPPING_MIN_WALL_TIME: f64 = 1.0;
+
+/// CPU time and peak memory of the compile and link steps that have task metrics,
+/// durations are in seconds and memory in bytes
+#[derive(Debug, Serialize)]
+pub struct ResourceReport {
+    /// Highest peak memory first
+    pub by_memory: Vec<StepResources>,
+    /// Most CPU time first
+    pub by_cpu_time: Vec<StepResources>,
+    pub targets: Vec<TargetResources>,
+    /// Steps with a high peak memory that got little CPU time for their wall time
+    pub likely_swapping: Vec<StepResources>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResources {
+    pub target: Option<String>,
+    pub kind: StepKind,
+    pub title: String,
+    pub wall_time: f64,
+    pub cpu_time: f64,
+    pub max_rss: u64,
+    /// CPU time divided by wall time
+    pub cpu_ratio: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TargetResources {
+    pub target: String,
+    pub steps: usize,
+    /// From the start of the first to the end of the last measured step
+    pub wall_time: f64,
+    pub cpu_time: f64,
+    pub max_rss: u64,
+    /// CPU time divided by wall time, the average number of busy cores
+    pub parallel_efficiency: f64,
+}
+
+fn is_compile_or_link(kind: &StepKind) -> bool {
+    kind.is_swift_compile()
+        || kind.is_clang_compile()
+        || matches!(
+            kind,
+            StepKind::SwiftEmitModule | StepKind::Ld | StepKind::Libtool
+        )
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    match denominator > 0.0 {
+        true => numerator / denominator,
+        false => 0.0,
+    }
+}
+
+fn is_likely_swapping(step: &StepResources) -> bool {
+    step.max_rss >= SWAPPING_MIN_RSS
+        && step.wall_time >= SWAPPING_MIN_WALL_TIME
+        && step.cpu_ratio < SWAPPING_MAX_CPU_RATIO
+}
+
+/// Builds the resource report from the task metrics attachments of the log
+pub fn resource_report(objects: &[XActivityLogObject]) -> ResourceReport {
+    let mut steps = vec![];
+    let mut targets = BTreeMap::<String, (TargetResources, f64, f64)>::new();
+    // Driver steps like `CompileSwiftSources` contain the per-file steps, measuring
+    // both would count their CPU time twice
+    let leaf_steps =
+        walk_sections(objects).filter(|n| n.is_step() && n.section.sub_sections.is_empty());
+    for node in leaf_steps {
+        let kind = node.section.step_info().kind;
+        if !is_compile_or_link(&kind) {
+            continue;
+        }
+        let Some(metrics) = node.section.task_metrics() else {
+            continue;
+        };
+        let wall_time = match metrics.wc_duration {
+            0 => node.section.duration().as_seconds_f64(),
+            _ => metrics.wall_time(),
+        };
+        let step = StepResources {
+            target: node.target_name().map(str::to_string),
+            kind,
+            title: node.section.title.clone(),
+            wall_time,
+            cpu_time: metrics.cpu_time(),
+            max_rss: metrics.max_rss,
+            cpu_ratio: ratio(metrics.cpu_time(), wall_time),
+        };
+
+        let start = node.section.time_started_recording.unix_timestamp_nanos() as f64 / 1e9;
+        let name = step.target.as_deref().unwrap_or("<no target>");
+        let (target, first_start, last_end) = targets.entry(name.to_string()).or_insert((
+            TargetResources {
+                target: name.to_string(),
+                steps: 0,
+                wall_time: 0.0,
+                cpu_time: 0.0,
+                max_rss: 0,
+                parallel_efficiency: 0.0,
+            },
+            f64::MAX,
+            f64::MIN,
+        ));
+        target.steps += 1;
+        target.cpu_time += step.cpu_time;
+        target.max_rss = target.max_rss.max(step.max_rss);
+        *first_start = first_start.min(start);
+        *last_end = last_end.max(start + wall_time);
+        steps.push(step);
+    }
+
+    let mut targets = targets
+        .into_values()
+        .map(|(mut target, first_start, last_end)| {
+            target.wall_time = last_end - first_start;
+            target.parallel_efficiency = ratio(target.cpu_time, target.wall_time);
+            target
+        })
+        .collect::<Vec<_>>();
+    targets.sort_by(|a, b| b.cpu_time.total_cmp(&a.cpu_time));
+
+    let mut likely_swapping = steps
+        .iter()
+        .filter(|s| is_likely_swapping(s))
+        .cloned()
+        .collect::<Vec<_>>();
+    likely_swapping.sort_by_key(|s| std::cmp::Reverse(s.max_rss));
+    let mut by_cpu_time = steps.clone();
+    by_cpu_time.sort_by(|a, b| b.cpu_time.total_cmp(&a.cpu_time));
+    let mut by_memory = steps;
+    by_memory.sort_by_key(|s| std::cmp::Reverse(s.max_rss));
+
+    ResourceReport {
+        by_memory,
+        by_cpu_time,
+        targets,
+        likely_swapping,
+    }
+}
+
+fn write_steps(f: &mut fmt::Formatter<'_>, header: &str, steps: &[StepResources]) -> fmt::Result {
+    writeln!(f, "{header} ({}):", steps.len())?;
+    for step in steps.iter().take(TEXT_REPORT_LIMIT) {
+        writeln!(
+            f,
+            "  {:>9} {:>8.2}s cpu {:>8.2}s wall {:>5.0}%  [{}] {}",
+            format_bytes(step.max_rss),
+            step.cpu_time,
+            step.wall_time,
+            step.cpu_ratio * 100.0,
+            step.target.as_deref().unwrap_or("<no target>"),
+            step.title
+        )?;
+    }
+    if steps.len() > TEXT_REPORT_LIMIT {
+        writeln!(f, "  ... and {} more", steps.len() - TEXT_REPORT_LIMIT)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for ResourceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "  {:<32} {:>6} {:>10} {:>10} {:>9} {:>10}",
+            "Target", "steps", "cpu", "wall", "peak RSS", "efficiency"
+        )?;
+        for target in self.targets.iter() {
+            writeln!(
+                f,
+                "  {:<32} {:>6} {:>9.2}s {:>9.2}s {:>9} {:>10.2}",
+                target.target,
+                target.steps,
+                target.cpu_time,
+                target.wall_time,
+                format_bytes(target.max_rss),
+                target.parallel_efficiency
+            )?;
+        }
+        writeln!(f)?;
+        write_steps(f, "Peak memory", &self.by_memory)?;
+        writeln!(f)?;
+        write_steps(f, "CPU time", &self.by_cpu_time)?;
+        writeln!(f)?;
+        write_steps(f, "Likely swapping", &self.likely_swapping)
+    }
+}
+
+fn write_steps_markdown(
+    f: &mut fmt::Formatter<'_>,
+    header: &str,
+    steps: &[StepResources],
+) -> fmt::Result {
+    writeln!(f, "**{header} ({})**\n", steps.len())?;
+    if steps.is_empty() {
+        return Ok(());
+    }
+    write_table_header(
+        f,
+        &["Peak RSS", "CPU", "Wall", "CPU/wall", "Target", "Step"],
+    )?;
+    for step in steps.iter().take(TEXT_REPORT_LIMIT) {
+        writeln!(
+            f,
+            "| {} | {:.2}s | {:.2}s | {:.0}% | {} | {} |",
+            format_bytes(step.max_rss),
+            step.cpu_time,
+            step.wall_time,
+            step.cpu_ratio * 100.0,
+            escape_cell(step.target.as_deref().unwrap_or("")),
+            escape_cell(&step.title)
+        )?;
+    }
+    if steps.len() > TEXT_REPORT_LIMIT {
+        writeln!(f, "\n... and {} more", steps.len() - TEXT_REPORT_LIMIT)?;
+    }
+    Ok(())
+}
+
+impl ToMarkdown for ResourceReport {
+    fn fmt_markdown(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_table_header(
+            f,
+            &["Target", "Steps", "CPU", "Wall", "Peak RSS", "Efficiency"],
+        )?;
+        for target in self.targets.iter() {
+            writeln!(
+                f,
+                "| {} | {} | {:.2}s | {:.2}s | {} | {:.2} |",
+                escape_cell(&target.target),
+                target.steps,
+                target.cpu_time,
+                target.wall_time,
+                format_bytes(target.max_rss),
+                target.parallel_efficiency
+            )?;
+        }
+        writeln!(f)?;
+        write_steps_markdown(f, "Peak memory", &self.by_memory)?;
+        writeln!(f)?;
+        write_steps_markdown(f, "CPU time", &self.by_cpu_time)?;
+        writeln!(f)?;
+        write_steps_markdown(f, "Likely swapping", &self.likely_swapping)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attachment::TASK_METRICS_IDENTIFIER;
+    use crate::log_class::{IDEActivityLogSection, IDEActivityLogSectionAttachment};
+    use serde_json::json;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    /// Step of `target` starting `start` seconds into the build, times are in seconds
+    fn step(
+        target: &str,
+        signature: &str,
+        start: i64,
+        wall_time: f64,
+        cpu_time: f64,
+        max_rss: u64,
+    ) -> IDEActivityLogSection {
+        let mut section =
+            IDEActivityLogSection::test_step_in(target, signature, 1).test_shifted(start);
+        section.attachments = vec![IDEActivityLogSectionAttachment {
+            identifier: TASK_METRICS_IDENTIFIER.to_string(),
+            major_version: 1,
+            minor_version: 0,
+            unknown1: json!({
+                "utime": (cpu_time * 1e6) as u64,
+                "stime": 0,
+                "maxRSS": max_rss,
+                "wcStartTime": 0,
+                "wcDuration": (wall_time * 1e6) as u64,
+            }),
+        }];
+        section
+    }
+
+    #[test]
+    fn measures_only_leaf_steps() {
+        let mut driver = step("App", "CompileSwiftSources normal arm64", 0, 4.0, 6.0, GB);
+        driver.sub_sections = vec![
+            step(
+                "App",
+                "SwiftCompile normal arm64 /src/A.swift",
+                0,
+                2.0,
+                2.0,
+                GB / 2,
+            ),
+            step(
+                "App",
+                "SwiftCompile normal arm64 /src/B.swift",
+                0,
+                3.0,
+                3.0,
+                GB / 4,
+            ),
+        ];
+        let report = resource_report(&[IDEActivityLogSection::test_build(vec![driver]).into()]);
+        assert_eq!(report.by_cpu_time.len(), 2);
+        assert!(report
+            .by_cpu_time
+            .iter()
+            .all(|s| s.kind == StepKind::SwiftCompile));
+        assert_eq!(report.by_memory[0].max_rss, GB / 2);
+        assert_eq!(report.targets[0].steps, 2);
+        assert_eq!(report.targets[0].cpu_time, 5.0);
+    }
+
+    #[test]
+    fn computes_parallel_efficiency_of_targets() {
+        // Two steps of 4s each running side by side, then one of 2s: 10s of CPU in 6s
+        let report = resource_report(&[IDEActivityLogSection::test_build(vec![
+            step(
+                "App",
+                "SwiftCompile normal arm64 /src/A.swift",
+                0,
+                4.0,
+                4.0,
+                GB,
+            ),
+            step(
+                "App",
+                "SwiftCompile normal arm64 /src/B.swift",
+                0,
+                4.0,
+                4.0,
+                GB,
+            ),
+            step("App", "Ld /Build/App normal", 4, 2.0, 2.0, GB),
+            step("Lib", "Libtool /Build/libLib.a normal", 0, 1.0, 0.5, GB),
+        ])
+        .into()]);
+        let app = &report.targets[0];
+        assert_eq!(app.target, "App");
+        assert_eq!(app.steps, 3);
+        assert_eq!(app.wall_time, 6.0);
+        assert!((app.parallel_efficiency - 10.0 / 6.0).abs() < 1e-9);
+        let lib = &report.targets[1];
+        assert_eq!(lib.target, "Lib");
+        assert_eq!(lib.parallel_efficiency, 0.5);
+    }
+
+    #[test]
+    fn falls_back_to_the_section_duration() {
+        let report = resource_report(&[IDEActivityLogSection::test_build(vec![step(
+            "App",
+            "SwiftCompile normal arm64 /src/A.swift",
+            0,
+            0.0,
+            0.5,
+            GB,
+        )])
+        .into()]);
+        let measured = &report.by_cpu_time[0];
+        assert_eq!(measured.wall_time, 1.0);
+        assert_eq!(measured.cpu_ratio, 0.5);
+    }
+
+    #[test]
+    fn flags_steps_waiting_for_memory() {
+        let compile = "SwiftCompile normal arm64 /src/A.swift";
+        let report = resource_report(&[IDEActivityLogSection::test_build(vec![
+            // Swapping: a lot of memory and less than half a CPU
+            step("App", compile, 0, 10.0, 2.0, 3 * GB),
+            // Too little memory
+            step("App", compile, 0, 10.0, 2.0, GB),
+            // Busy enough
+            step("App", compile, 0, 10.0, 6.0, 3 * GB),
+            // Too short to tell
+            step("App", compile, 0, 0.5, 0.1, 3 * GB),
+        ])
+        .into()]);
+        assert_eq!(report.by_memory.len(), 4);
+        assert_eq!(report.likely_swapping.len(), 1);
+        let swapping = &report.likely_swapping[0];
+        assert_eq!(swapping.max_rss, 3 * GB);
+        assert_eq!(swapping.cpu_ratio, 0.2);
+
+        let at_threshold = StepResources {
+            max_rss: SWAPPING_MIN_RSS,
+            wall_time: SWAPPING_MIN_WALL_TIME,
+            cpu_ratio: SWAPPING_MAX_CPU_RATIO,
+            ..swapping.clone()
+        };
+        assert!(!is_likely_swapping(&at_threshold));
+        assert!(is_likely_swapping(&StepResources {
+            cpu_ratio: SWAPPING_MAX_CPU_RATIO - 0.01,
+            ..at_threshold
+        }));
+    }
+}
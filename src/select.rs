@@ -0,0 +1,840 @@
+use serde::Serialize;
+use std::str::FromStr;
+
+use crate::deser::XActivityLogObject;
+use crate::log_class::{IDEActivityLogMessage, IDEActivityLogSection, IDECommandLineBuildLog};
+use crate::walk::SectionNode;
+
+/// Selector picking nodes of the log tree, e.g.
+///
+/// ```text
+/// section[title~="Compile"][duration>2s]/message[severity=error]
+/// ```
+///
+/// A selector is a chain of steps separated by `/` (child) or `//` (descendant).
+/// The first step matches at any depth. A step names the node kind, `log`, `section`,
+/// `message` or `*`, followed by predicates `[field op value]` that all have to hold.
+/// Operators are `=`, `!=`, `~=` (contains), `^=` (starts with), `$=` (ends with)
+/// and `<`, `<=`, `>`, `>=` for numbers. Durations take an `ms`, `s` or `m` suffix.
+/// Fields are `title`, `signature`, `type`, `domain`, `step`, `target`, `duration`,
+/// `cached`, `cancelled`, `severity`, `file`, `line` and `category`, a node without
+/// the field doesn't match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    /// Whether the step matches any descendant of the previous step rather than a child
+    descendant: bool,
+    kind: NodeKind,
+    predicates: Vec<Predicate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Any,
+    Log,
+    Section,
+    Message,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Predicate {
+    field: Field,
+    op: Op,
+    value: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Title,
+    Signature,
+    /// Section type of sections, message type of messages
+    Type,
+    Domain,
+    /// Kind of a build step, e.g. `SwiftCompile`
+    Step,
+    Target,
+    /// In seconds
+    Duration,
+    Cached,
+    Cancelled,
+    Severity,
+    File,
+    Line,
+    Category,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    NotEq,
+    Contains,
+    StartsWith,
+    EndsWith,
+    Less,
+    LessOrEq,
+    Greater,
+    GreaterOrEq,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+}
+
+/// Node matched by a selector, serialized like the objects of the log
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum SelectedNode<'a> {
+    IDECommandLineBuildLog(&'a IDECommandLineBuildLog),
+    IDEActivityLogSection(&'a IDEActivityLogSection),
+    IDEActivityLogMessage(&'a IDEActivityLogMessage),
+}
+
+impl Field {
+    fn parse(name: &str) -> anyhow::Result<Self> {
+        Ok(match name {
+            "title" => Field::Title,
+            "signature" => Field::Signature,
+            "type" => Field::Type,
+            "domain" => Field::Domain,
+            "step" => Field::Step,
+            "target" => Field::Target,
+            "duration" => Field::Duration,
+            "cached" => Field::Cached,
+            "cancelled" => Field::Cancelled,
+            "severity" => Field::Severity,
+            "file" => Field::File,
+            "line" => Field::Line,
+            "category" => Field::Category,
+            _ => anyhow::bail!("Unknown field {name:?}"),
+        })
+    }
+
+    /// Value of the field for the last node of `path`, `None` if the node has no such field
+    fn value(&self, path: &[SelectedNode]) -> Option<Value> {
+        let node = path.last()?;
+        let text = |s: &str| Some(Value::Text(s.to_string()));
+        match (self, node) {
+            (Field::Title, SelectedNode::IDECommandLineBuildLog(log)) => text(&log.title),
+            (Field::Title, SelectedNode::IDEActivityLogSection(section)) => text(&section.title),
+            (Field::Title, SelectedNode::IDEActivityLogMessage(message)) => text(&message.title),
+            (Field::Signature, SelectedNode::IDECommandLineBuildLog(log)) => text(&log.signature),
+            (Field::Signature, SelectedNode::IDEActivityLogSection(section)) => {
+                text(&section.signature)
+            }
+            (Field::Type, SelectedNode::IDECommandLineBuildLog(log)) => {
+                text(&log.section_type.to_string())
+            }
+            (Field::Type, SelectedNode::IDEActivityLogSection(section)) => {
+                text(&section.section_type.to_string())
+            }
+            (Field::Type, SelectedNode::IDEActivityLogMessage(message)) => {
+                message.r#type.as_deref().and_then(text)
+            }
+            (Field::Domain, SelectedNode::IDECommandLineBuildLog(log)) => {
+                text(&log.domain_type.to_string())
+            }
+            (Field::Domain, SelectedNode::IDEActivityLogSection(section)) => {
+                text(&section.domain_type.to_string())
+            }
+            (Field::Step, SelectedNode::IDEActivityLogSection(section)) => {
+                text(section.step_info().kind.as_str())
+            }
+            (Field::Target, _) => section_node(path)?.target_name().and_then(text),
+            (Field::Duration, SelectedNode::IDECommandLineBuildLog(log)) => Some(Value::Number(
+                (log.time_stopped_recording - log.time_started_recording).as_seconds_f64(),
+            )),
+            (Field::Duration, SelectedNode::IDEActivityLogSection(section)) => {
+                Some(Value::Number(section.duration().as_seconds_f64()))
+            }
+            (Field::Cached, SelectedNode::IDEActivityLogSection(section)) => {
+                Some(Value::Bool(section.was_fetched_from_cache))
+            }
+            (Field::Cancelled, SelectedNode::IDEActivityLogSection(section)) => {
+                Some(Value::Bool(section.was_cancelled))
+            }
+            (Field::Severity, SelectedNode::IDEActivityLogMessage(message)) => {
+                text(&message.severity.to_string())
+            }
+            (Field::File, SelectedNode::IDEActivityLogMessage(message)) => {
+                let path = message.location.as_ref()?.file_path();
+                (!path.is_empty()).then(|| Value::Text(path.to_string()))
+            }
+            (Field::File, SelectedNode::IDEActivityLogSection(section)) => {
+                section.step_info().source_file.map(Value::Text)
+            }
+            (Field::Line, SelectedNode::IDEActivityLogMessage(message)) => {
+                let (line, _) = message.location.as_ref()?.line_and_column()?;
+                Some(Value::Number(line as f64))
+            }
+            (Field::Category, SelectedNode::IDEActivityLogMessage(message)) => {
+                message.category_ident.as_deref().and_then(text)
+            }
+            _ => None,
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Field::Duration | Field::Line)
+    }
+
+    fn is_bool(&self) -> bool {
+        matches!(self, Field::Cached | Field::Cancelled)
+    }
+}
+
+/// Closest section of `path` with the sections it is nested in
+fn section_node<'a>(path: &[SelectedNode<'a>]) -> Option<SectionNode<'a>> {
+    let mut ancestors = path
+        .iter()
+        .filter_map(|node| match node {
+            SelectedNode::IDEActivityLogSection(section) => Some(*section),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let section = ancestors.pop()?;
+    Some(SectionNode { section, ancestors })
+}
+
+/// Seconds of a duration like `2s`, `150ms`, `1.5m` or `2`
+fn parse_number(text: &str, field: Field) -> anyhow::Result<f64> {
+    let (number, scale) = match field {
+        Field::Duration => {
+            if let Some(number) = text.strip_suffix("ms") {
+                (number, 0.001)
+            } else if let Some(number) = text.strip_suffix('s') {
+                (number, 1.0)
+            } else if let Some(number) = text.strip_suffix('m') {
+                (number, 60.0)
+            } else {
+                (text, 1.0)
+            }
+        }
+        _ => (text, 1.0),
+    };
+    match number.trim().parse::<f64>() {
+        Ok(number) => Ok(number * scale),
+        Err(_) => anyhow::bail!("Expected a number, got {text:?}"),
+    }
+}
+
+impl Predicate {
+    fn new(field: Field, op: Op, text: String, quoted: bool) -> anyhow::Result<Self> {
+        let is_ordering = matches!(op, Op::Less | Op::LessOrEq | Op::Greater | Op::GreaterOrEq);
+        let is_text_op = matches!(op, Op::Contains | Op::StartsWith | Op::EndsWith);
+        let value = if field.is_numeric() && !quoted {
+            if is_text_op {
+                anyhow::bail!("{field:?} is a number, only =, !=, <, <=, > and >= apply");
+            }
+            Value::Number(parse_number(&text, field)?)
+        } else if field.is_bool() {
+            if is_ordering || is_text_op {
+                anyhow::bail!("{field:?} is true or false, only = and != apply");
+            }
+            match text.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => anyhow::bail!("Expected true or false, got {text:?}"),
+            }
+        } else {
+            if is_ordering {
+                anyhow::bail!("{field:?} is text, <, <=, > and >= don't apply");
+            }
+            Value::Text(text)
+        };
+        Ok(Predicate { field, op, value })
+    }
+
+    fn matches(&self, path: &[SelectedNode]) -> bool {
+        let Some(actual) = self.field.value(path) else {
+            return false;
+        };
+        match (&actual, &self.value) {
+            (Value::Text(actual), Value::Text(expected)) => match self.op {
+                Op::Eq => actual == expected,
+                Op::NotEq => actual != expected,
+                Op::Contains => actual.contains(expected.as_str()),
+                Op::StartsWith => actual.starts_with(expected.as_str()),
+                Op::EndsWith => actual.ends_with(expected.as_str()),
+                _ => false,
+            },
+            (Value::Number(actual), Value::Number(expected)) => match self.op {
+                Op::Eq => actual == expected,
+                Op::NotEq => actual != expected,
+                Op::Less => actual < expected,
+                Op::LessOrEq => actual <= expected,
+                Op::Greater => actual > expected,
+                Op::GreaterOrEq => actual >= expected,
+                _ => false,
+            },
+            // A quoted number compares as text
+            (Value::Number(actual), Value::Text(expected)) => match self.op {
+                Op::Eq => actual.to_string() == *expected,
+                Op::NotEq => actual.to_string() != *expected,
+                _ => false,
+            },
+            (Value::Bool(actual), Value::Bool(expected)) => match self.op {
+                Op::Eq => actual == expected,
+                Op::NotEq => actual != expected,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Step {
+    fn matches(&self, path: &[SelectedNode]) -> bool {
+        let kind_matches = matches!(
+            (self.kind, path.last()),
+            (NodeKind::Any, Some(_))
+                | (NodeKind::Log, Some(SelectedNode::IDECommandLineBuildLog(_)))
+                | (
+                    NodeKind::Section,
+                    Some(SelectedNode::IDEActivityLogSection(_))
+                )
+                | (
+                    NodeKind::Message,
+                    Some(SelectedNode::IDEActivityLogMessage(_))
+                )
+        );
+        kind_matches && self.predicates.iter().all(|p| p.matches(path))
+    }
+}
+
+/// Reads a selector one character at a time
+struct SelectorParser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> SelectorParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.position += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, expected: &str) -> anyhow::Error {
+        anyhow::anyhow!(
+            "Expected {expected} at position {} of selector {:?}",
+            self.position,
+            self.text
+        )
+    }
+
+    fn word(&mut self) -> &'a str {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '*'))
+            .unwrap_or(rest.len());
+        self.position += end;
+        &rest[..end]
+    }
+
+    fn op(&mut self) -> anyhow::Result<Op> {
+        // Two character operators first so that `>=` isn't read as `>`
+        let ops = [
+            ("~=", Op::Contains),
+            ("^=", Op::StartsWith),
+            ("$=", Op::EndsWith),
+            ("!=", Op::NotEq),
+            (">=", Op::GreaterOrEq),
+            ("<=", Op::LessOrEq),
+            ("=", Op::Eq),
+            (">", Op::Greater),
+            ("<", Op::Less),
+        ];
+        match ops.iter().find(|(token, _)| self.eat(token)) {
+            Some((_, op)) => Ok(*op),
+            None => Err(self.error("an operator")),
+        }
+    }
+
+    /// Quoted or bare value, returns whether it was quoted
+    fn value(&mut self) -> anyhow::Result<(String, bool)> {
+        if !self.eat("\"") {
+            let rest = self.rest();
+            let end = rest.find(']').ok_or_else(|| self.error("]"))?;
+            self.position += end;
+            return Ok((rest[..end].trim().to_string(), false));
+        }
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position += i + 1;
+                    return Ok((value, true));
+                }
+                '\\' => match chars.next() {
+                    Some((_, next)) => value.push(next),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        self.position = self.text.len();
+        Err(self.error("a closing quote"))
+    }
+
+    fn predicate(&mut self) -> anyhow::Result<Predicate> {
+        self.skip_whitespace();
+        let field = Field::parse(self.word())?;
+        self.skip_whitespace();
+        let op = self.op()?;
+        self.skip_whitespace();
+        let (value, quoted) = self.value()?;
+        self.skip_whitespace();
+        if !self.eat("]") {
+            return Err(self.error("]"));
+        }
+        Predicate::new(field, op, value, quoted)
+    }
+
+    fn step(&mut self, descendant: bool) -> anyhow::Result<Step> {
+        self.skip_whitespace();
+        let kind = match self.word() {
+            "*" => NodeKind::Any,
+            "log" => NodeKind::Log,
+            "section" => NodeKind::Section,
+            "message" => NodeKind::Message,
+            "" => return Err(self.error("log, section, message or *")),
+            name => {
+                anyhow::bail!("Unknown node kind {name:?}, expected log, section, message or *")
+            }
+        };
+        let mut predicates = vec![];
+        while self.eat("[") {
+            predicates.push(self.predicate()?);
+        }
+        Ok(Step {
+            descendant,
+            kind,
+            predicates,
+        })
+    }
+
+    fn selector(&mut self) -> anyhow::Result<Selector> {
+        let mut steps = vec![self.step(true)?];
+        loop {
+            self.skip_whitespace();
+            if self.rest().is_empty() {
+                return Ok(Selector { steps });
+            }
+            let descendant = if self.eat("//") {
+                true
+            } else if self.eat("/") {
+                false
+            } else {
+                return Err(self.error("/ or //"));
+            };
+            steps.push(self.step(descendant)?);
+        }
+    }
+}
+
+impl Selector {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        SelectorParser { text, position: 0 }.selector()
+    }
+
+    /// Steps that the last node of `path` matches, `parent` being the state of its parent.
+    /// Keeping the state of every ancestor makes `//` linear in the depth of the tree.
+    fn match_state(&self, path: &[SelectedNode], parent: Option<&MatchState>) -> MatchState {
+        let mut state = MatchState {
+            matched: vec![false; self.steps.len()],
+            reached: vec![false; self.steps.len()],
+        };
+        for (i, step) in self.steps.iter().enumerate() {
+            let follows = match (i, parent) {
+                (0, _) => true,
+                (_, None) => false,
+                (_, Some(parent)) if step.descendant => parent.reached[i - 1],
+                (_, Some(parent)) => parent.matched[i - 1],
+            };
+            state.matched[i] = follows && step.matches(path);
+            state.reached[i] = state.matched[i] || parent.is_some_and(|p| p.reached[i]);
+        }
+        state
+    }
+
+    fn visit<'a>(
+        &self,
+        path: &mut Vec<SelectedNode<'a>>,
+        position: &mut NodePosition,
+        parent: Option<&MatchState>,
+        selected: &mut Vec<(NodePosition, SelectedNode<'a>)>,
+    ) {
+        let Some(node) = path.last().copied() else {
+            return;
+        };
+        let state = self.match_state(path, parent);
+        if state.matched[self.steps.len() - 1] {
+            // Descendants are part of the node already
+            selected.push((position.clone(), node));
+            return;
+        }
+        let (sections, messages) = match node {
+            SelectedNode::IDECommandLineBuildLog(log) => (&log.sub_sections[..], &[][..]),
+            SelectedNode::IDEActivityLogSection(section) => {
+                (&section.sub_sections[..], &section.messages[..])
+            }
+            SelectedNode::IDEActivityLogMessage(message) => (&[][..], &message.sub_messages[..]),
+        };
+        let children = sections
+            .iter()
+            .map(SelectedNode::IDEActivityLogSection)
+            .chain(messages.iter().map(SelectedNode::IDEActivityLogMessage));
+        for (i, child) in children.enumerate() {
+            path.push(child);
+            position.push(i);
+            self.visit(path, position, Some(&state), selected);
+            position.pop();
+            path.pop();
+        }
+    }
+
+    fn select_positions<'a>(
+        &self,
+        objects: &'a [XActivityLogObject],
+    ) -> Vec<(NodePosition, SelectedNode<'a>)> {
+        let mut selected = vec![];
+        for (i, object) in objects.iter().enumerate() {
+            let root = match object {
+                XActivityLogObject::IDECommandLineBuildLog(log) => {
+                    SelectedNode::IDECommandLineBuildLog(log)
+                }
+                XActivityLogObject::IDEActivityLogSection(section) => {
+                    SelectedNode::IDEActivityLogSection(section)
+                }
+                XActivityLogObject::IDEActivityLogMessage(message) => {
+                    SelectedNode::IDEActivityLogMessage(message)
+                }
+                _ => continue,
+            };
+            self.visit(&mut vec![root], &mut vec![i], None, &mut selected);
+        }
+        selected
+    }
+
+    /// Nodes of the log that match the selector, in the order of the log.
+    /// Nodes nested in a matched node aren't repeated, they are part of it.
+    pub fn select<'a>(&self, objects: &'a [XActivityLogObject]) -> Vec<SelectedNode<'a>> {
+        self.select_positions(objects)
+            .into_iter()
+            .map(|(_, node)| node)
+            .collect()
+    }
+
+    /// Takes the nodes that match the selector out of the log, in the order of the log
+    pub fn extract(&self, mut objects: Vec<XActivityLogObject>) -> Vec<XActivityLogObject> {
+        let positions = self
+            .select_positions(&objects)
+            .into_iter()
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+        // From the end so that taking a node doesn't move the nodes still to take
+        let mut extracted = positions
+            .iter()
+            .rev()
+            .map(|position| take_node(&mut objects, position))
+            .collect::<Vec<_>>();
+        extracted.reverse();
+        extracted
+    }
+}
+
+/// Index of the object in the log followed by the index of the child at every level,
+/// sub sections counting before messages
+type NodePosition = Vec<usize>;
+
+/// Steps of the selector a node matches, and the ones it or one of its ancestors matches
+struct MatchState {
+    matched: Vec<bool>,
+    reached: Vec<bool>,
+}
+
+/// Removes the node at `position` from the log
+fn take_node(objects: &mut Vec<XActivityLogObject>, position: &[usize]) -> XActivityLogObject {
+    let (&i, rest) = position.split_first().expect("positions aren't empty");
+    if rest.is_empty() {
+        return objects.remove(i);
+    }
+    match &mut objects[i] {
+        XActivityLogObject::IDECommandLineBuildLog(log) => {
+            take_child(&mut log.sub_sections, &mut vec![], rest)
+        }
+        XActivityLogObject::IDEActivityLogSection(section) => {
+            take_child(&mut section.sub_sections, &mut section.messages, rest)
+        }
+        XActivityLogObject::IDEActivityLogMessage(message) => {
+            take_child(&mut vec![], &mut message.sub_messages, rest)
+        }
+        _ => unreachable!("only logs, sections and messages have children"),
+    }
+}
+
+/// Removes the node at `position` below a node with the given children
+fn take_child(
+    sections: &mut Vec<IDEActivityLogSection>,
+    messages: &mut Vec<IDEActivityLogMessage>,
+    position: &[usize],
+) -> XActivityLogObject {
+    let (&i, rest) = position.split_first().expect("positions aren't empty");
+    match (i.checked_sub(sections.len()), rest.is_empty()) {
+        (None, true) => XActivityLogObject::IDEActivityLogSection(sections.remove(i)),
+        (Some(i), true) => XActivityLogObject::IDEActivityLogMessage(messages.remove(i)),
+        (None, false) => {
+            let section = &mut sections[i];
+            take_child(&mut section.sub_sections, &mut section.messages, rest)
+        }
+        (Some(i), false) => take_child(&mut vec![], &mut messages[i].sub_messages, rest),
+    }
+}
+
+impl FromStr for Selector {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Selector::parse(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_types::{SectionType, Severity};
+    use time::Duration;
+
+    fn section(title: &str, signature: &str, seconds: f64) -> IDEActivityLogSection {
+        let mut section = IDEActivityLogSection::test_step(title, signature, None);
+        section.time_stopped_recording =
+            section.time_started_recording + Duration::seconds_f64(seconds);
+        section
+    }
+
+    /// Build with one target, a Swift step with an error and a warning
+    /// and a cached C step
+    fn log() -> Vec<XActivityLogObject> {
+        let mut swift = section(
+            "Compile App.swift",
+            "SwiftCompile normal arm64 /src/App.swift (in target 'MyApp' from project 'MyApp')",
+            3.0,
+        );
+        swift.messages = vec![
+            IDEActivityLogMessage::test_message("Cannot find 'baz' in scope", Severity::Error),
+            IDEActivityLogMessage::test_message("Variable 'x' was never used", Severity::Warning),
+        ];
+        let mut c = section(
+            "Compile util.c",
+            "CompileC /Build/util.o /src/util.c normal arm64 c com.apple.compilers.llvm.clang.1_0.compiler (in target 'MyApp' from project 'MyApp')",
+            0.75,
+        );
+        c.was_fetched_from_cache = true;
+        let mut target = section(
+            "Build target MyApp of project MyApp with configuration Debug",
+            "",
+            0.25,
+        );
+        target.section_type = SectionType::Target;
+        target.sub_sections = vec![swift, c];
+        let mut build = section("Build MyApp", "", 0.25);
+        build.section_type = SectionType::Main;
+        build.sub_sections = vec![target];
+        vec![XActivityLogObject::IDEActivityLogSection(build)]
+    }
+
+    fn title<'a>(node: &SelectedNode<'a>) -> &'a str {
+        match node {
+            SelectedNode::IDECommandLineBuildLog(log) => &log.title,
+            SelectedNode::IDEActivityLogSection(section) => &section.title,
+            SelectedNode::IDEActivityLogMessage(message) => &message.title,
+        }
+    }
+
+    #[test]
+    fn selects_matching_nodes() {
+        let log = log();
+        let cases: &[(&str, &[&str])] = &[
+            (r#"section[title~="util"]"#, &["Compile util.c"]),
+            (
+                r#"section[title^="Compile"][title$=".swift"]"#,
+                &["Compile App.swift"],
+            ),
+            ("section[duration>2s]", &["Compile App.swift"]),
+            (
+                "section[duration>500ms]",
+                &["Compile App.swift", "Compile util.c"],
+            ),
+            (
+                "section[duration<=250ms][type=target]",
+                &["Build target MyApp of project MyApp with configuration Debug"],
+            ),
+            ("section[cached=true]", &["Compile util.c"]),
+            (
+                "section[cached!=true][step=SwiftCompile]",
+                &["Compile App.swift"],
+            ),
+            ("message[severity=error]", &["Cannot find 'baz' in scope"]),
+            (
+                "message[severity=warning]",
+                &["Variable 'x' was never used"],
+            ),
+            (
+                "message[target=MyApp]",
+                &["Cannot find 'baz' in scope", "Variable 'x' was never used"],
+            ),
+            ("section[target=Other]", &[]),
+            ("section[file=/src/util.c]", &["Compile util.c"]),
+            // A node without the field doesn't match
+            ("section[severity=error]", &[]),
+        ];
+        for (selector, expected) in cases {
+            let selected = Selector::parse(selector).unwrap().select(&log);
+            let titles = selected.iter().map(title).collect::<Vec<_>>();
+            assert_eq!(titles, *expected, "{selector}");
+        }
+    }
+
+    #[test]
+    fn child_and_descendant_steps() {
+        let log = log();
+        let cases: &[(&str, &[&str])] = &[
+            (r#"section[type=target]/message"#, &[]),
+            (
+                r#"section[type=target]//message"#,
+                &["Cannot find 'baz' in scope", "Variable 'x' was never used"],
+            ),
+            (
+                r#"section[type=target]/section/message[severity=error]"#,
+                &["Cannot find 'baz' in scope"],
+            ),
+            (r#"section[type=main]/section[cached=true]"#, &[]),
+            (
+                r#"section[type=main]//section[cached=true]"#,
+                &["Compile util.c"],
+            ),
+            // The first step matches at any depth
+            (
+                r#"section[step=SwiftCompile]/message[severity=warning]"#,
+                &["Variable 'x' was never used"],
+            ),
+        ];
+        for (selector, expected) in cases {
+            let selected = Selector::parse(selector).unwrap().select(&log);
+            let titles = selected.iter().map(title).collect::<Vec<_>>();
+            assert_eq!(titles, *expected, "{selector}");
+        }
+    }
+
+    #[test]
+    fn nested_matches_are_part_of_the_outer_one() {
+        let log = log();
+        let titles = |selector: &str| {
+            let selected = Selector::parse(selector).unwrap().select(&log);
+            selected.iter().map(title).collect::<Vec<_>>()
+        };
+        assert_eq!(titles("section"), ["Build MyApp"]);
+        assert_eq!(
+            titles("section//section"),
+            ["Build target MyApp of project MyApp with configuration Debug"]
+        );
+        assert_eq!(
+            titles("*[duration>500ms]"),
+            ["Compile App.swift", "Compile util.c"]
+        );
+    }
+
+    #[test]
+    fn extracts_matching_nodes() {
+        let selected = Selector::parse("section[title^=Compile]//message")
+            .unwrap()
+            .extract(log());
+        let titles = selected
+            .iter()
+            .map(|object| match object {
+                XActivityLogObject::IDEActivityLogMessage(message) => message.title.as_str(),
+                _ => panic!("only messages match"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            ["Cannot find 'baz' in scope", "Variable 'x' was never used"]
+        );
+
+        let selected = Selector::parse("section[cached=true]")
+            .unwrap()
+            .extract(log());
+        assert!(matches!(
+            &selected[..],
+            [XActivityLogObject::IDEActivityLogSection(section)] if section.title == "Compile util.c"
+        ));
+    }
+
+    #[test]
+    fn deep_descendant_chains() {
+        let mut deepest = section("step", "", 1.0);
+        deepest.messages = vec![IDEActivityLogMessage::test_message("deep", Severity::Error)];
+        let root = (0..200).fold(deepest, |child, i| {
+            let mut parent = section(&format!("level {i}"), "", 1.0);
+            parent.sub_sections = vec![child];
+            parent
+        });
+        let log = vec![XActivityLogObject::IDEActivityLogSection(root)];
+        let selector = Selector::parse(
+            "section//section//section//section//section//section//message[title=missing]",
+        )
+        .unwrap();
+        assert!(selector.select(&log).is_empty());
+        let selector = Selector::parse("section//section//section//section//message").unwrap();
+        assert_eq!(selector.select(&log).len(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_selectors() {
+        let cases = [
+            ("section[colour=red]", "Unknown field \"colour\""),
+            (r#"section[title="Compile]"#, "a closing quote"),
+            ("section[title~=Compile", "]"),
+            ("folder[title=x]", "Unknown node kind \"folder\""),
+            ("section[duration~=2s]", "only =, !=, <, <=, > and >= apply"),
+            ("section[duration>fast]", "Expected a number"),
+            ("section[cached=yes]", "Expected true or false"),
+            ("section[title>b]", "don't apply"),
+            ("section[title=a] message", "/ or //"),
+            ("section/", "log, section, message or *"),
+            ("section///message", "log, section, message or *"),
+        ];
+        for (selector, expected) in cases {
+            let error = Selector::parse(selector).unwrap_err().to_string();
+            assert!(error.contains(expected), "{selector}: {error}");
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        let duration = |text: &str| parse_number(text, Field::Duration).unwrap();
+        assert_eq!(duration("2s"), 2.0);
+        assert_eq!(duration("500ms"), 0.5);
+        assert_eq!(duration("1.5m"), 90.0);
+        assert_eq!(duration("3"), 3.0);
+    }
+}
@@ -0,0 +1,141 @@
+//! SLF encoder building activity logs for the tests and the benchmarks,
+//! not part of the API.
+
+use crate::attachment::TASK_METRICS_IDENTIFIER;
+
+/// Writes SLF the way Xcode does, registering class names on first use.
+/// Sections are written in two parts around their sub sections,
+/// see [`SlfWriter::section_start`] and [`SlfWriter::section_end`].
+pub struct SlfWriter {
+    pub contents: Vec<u8>,
+    classes: Vec<&'static str>,
+}
+
+impl Default for SlfWriter {
+    fn default() -> Self {
+        Self {
+            contents: b"SLF0".to_vec(),
+            classes: vec![],
+        }
+    }
+}
+
+impl SlfWriter {
+    pub fn class_instance(&mut self, name: &'static str) {
+        let position = match self.classes.iter().position(|c| *c == name) {
+            Some(i) => i + 1,
+            None => {
+                self.classes.push(name);
+                self.contents
+                    .extend(format!("{}%{name}", name.len()).bytes());
+                self.classes.len()
+            }
+        };
+        self.contents.extend(format!("{position}@").bytes());
+    }
+
+    pub fn int(&mut self, value: u64) {
+        self.contents.extend(format!("{value}#").bytes());
+    }
+
+    pub fn double(&mut self, value: f64) {
+        for byte in value.to_le_bytes() {
+            self.contents.extend(format!("{byte:02x}").bytes());
+        }
+        self.contents.push(b'^');
+    }
+
+    pub fn string(&mut self, value: Option<&str>) {
+        match value {
+            Some(value) => self
+                .contents
+                .extend(format!("{}\"{value}", value.len()).bytes()),
+            None => self.null(),
+        }
+    }
+
+    pub fn json(&mut self, value: &str) {
+        self.contents
+            .extend(format!("{}*{value}", value.len()).bytes());
+    }
+
+    pub fn null(&mut self) {
+        self.contents.push(b'-');
+    }
+
+    pub fn array(&mut self, size: usize) {
+        match size {
+            0 => self.null(),
+            size => self.contents.extend(format!("{size}(").bytes()),
+        }
+    }
+
+    pub fn message(&mut self, title: &str, severity: u64) {
+        self.class_instance("IDEActivityLogMessage");
+        self.string(Some(title));
+        self.string(None);
+        self.int(0);
+        self.int(0);
+        self.int(0);
+        self.array(0);
+        self.int(severity);
+        self.string(None);
+        self.null();
+        self.string(None);
+        self.array(0);
+        self.string(None);
+    }
+
+    /// Fields of a section up to its sub sections, which must follow
+    pub fn section_start(
+        &mut self,
+        section_type: u64,
+        title: &str,
+        start: f64,
+        end: f64,
+        sub_sections: usize,
+    ) {
+        self.class_instance("IDEActivityLogSection");
+        self.int(section_type);
+        self.string(Some("com.apple.dt.IDE.BuildLogSection"));
+        self.string(Some(title));
+        self.string(Some(title));
+        self.double(start);
+        self.double(end);
+        self.array(sub_sections);
+    }
+
+    /// Fields of a section after its sub sections,
+    /// with a task metrics attachment holding `metrics` if any
+    pub fn section_end(
+        &mut self,
+        text: Option<&str>,
+        messages: &[(&str, u64)],
+        cached: bool,
+        metrics: Option<&str>,
+    ) {
+        self.string(text);
+        self.array(messages.len());
+        for (title, severity) in messages {
+            self.message(title, *severity);
+        }
+        self.int(0);
+        self.int(0);
+        self.int(cached as u64);
+        self.string(None);
+        self.null();
+        for _ in 0..4 {
+            self.string(None);
+        }
+        let Some(metrics) = metrics else {
+            return;
+        };
+        self.array(1);
+        self.class_instance("IDEActivityLogSectionAttachment");
+        self.string(Some(TASK_METRICS_IDENTIFIER));
+        self.int(1);
+        self.int(0);
+        self.json(metrics);
+        self.int(0);
+    }
+}
@@ -0,0 +1,268 @@
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+use crate::command::split_command_line;
+use crate::log_class::IDEActivityLogSection;
+
+/// Kind of a build step, named after the first word of its signature
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StepKind {
+    SwiftDriver,
+    /// `SwiftCompile`, called `CompileSwift` by older Xcode versions
+    SwiftCompile,
+    CompileSwiftSources,
+    SwiftEmitModule,
+    SwiftMergeGeneratedHeaders,
+    CompileC,
+    Ld,
+    Libtool,
+    CreateUniversalBinary,
+    CodeSign,
+    GenerateDSYMFile,
+    Strip,
+    CompileAssetCatalog,
+    CompileStoryboard,
+    CompileXIB,
+    LinkStoryboards,
+    ProcessInfoPlistFile,
+    ProcessProductPackaging,
+    PhaseScriptExecution,
+    Copy,
+    CpResource,
+    CopySwiftLibs,
+    Touch,
+    MkDir,
+    SymLink,
+    WriteAuxiliaryFile,
+    CreateBuildDirectory,
+    /// Any other first word of the signature
+    Other(String),
+}
+
+impl StepKind {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "SwiftDriver" => StepKind::SwiftDriver,
+            "SwiftCompile" | "CompileSwift" => StepKind::SwiftCompile,
+            "CompileSwiftSources" => StepKind::CompileSwiftSources,
+            "SwiftEmitModule" => StepKind::SwiftEmitModule,
+            "SwiftMergeGeneratedHeaders" => StepKind::SwiftMergeGeneratedHeaders,
+            "CompileC" => StepKind::CompileC,
+            "Ld" => StepKind::Ld,
+            "Libtool" => StepKind::Libtool,
+            "CreateUniversalBinary" => StepKind::CreateUniversalBinary,
+            "CodeSign" => StepKind::CodeSign,
+            "GenerateDSYMFile" => StepKind::GenerateDSYMFile,
+            "Strip" => StepKind::Strip,
+            "CompileAssetCatalog" => StepKind::CompileAssetCatalog,
+            "CompileStoryboard" => StepKind::CompileStoryboard,
+            "CompileXIB" => StepKind::CompileXIB,
+            "LinkStoryboards" => StepKind::LinkStoryboards,
+            "ProcessInfoPlistFile" => StepKind::ProcessInfoPlistFile,
+            "ProcessProductPackaging" => StepKind::ProcessProductPackaging,
+            "PhaseScriptExecution" => StepKind::PhaseScriptExecution,
+            "Copy" => StepKind::Copy,
+            "CpResource" => StepKind::CpResource,
+            "CopySwiftLibs" => StepKind::CopySwiftLibs,
+            "Touch" => StepKind::Touch,
+            "MkDir" => StepKind::MkDir,
+            "SymLink" => StepKind::SymLink,
+            "WriteAuxiliaryFile" => StepKind::WriteAuxiliaryFile,
+            "CreateBuildDirectory" => StepKind::CreateBuildDirectory,
+            other => StepKind::Other(other.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            StepKind::SwiftDriver => "SwiftDriver",
+            StepKind::SwiftCompile => "SwiftCompile",
+            StepKind::CompileSwiftSources => "CompileSwiftSources",
+            StepKind::SwiftEmitModule => "SwiftEmitModule",
+            StepKind::SwiftMergeGeneratedHeaders => "SwiftMergeGeneratedHeaders",
+            StepKind::CompileC => "CompileC",
+            StepKind::Ld => "Ld",
+            StepKind::Libtool => "Libtool",
+            StepKind::CreateUniversalBinary => "CreateUniversalBinary",
+            StepKind::CodeSign => "CodeSign",
+            StepKind::GenerateDSYMFile => "GenerateDSYMFile",
+            StepKind::Strip => "Strip",
+            StepKind::CompileAssetCatalog => "CompileAssetCatalog",
+            StepKind::CompileStoryboard => "CompileStoryboard",
+            StepKind::CompileXIB => "CompileXIB",
+            StepKind::LinkStoryboards => "LinkStoryboards",
+            StepKind::ProcessInfoPlistFile => "ProcessInfoPlistFile",
+            StepKind::ProcessProductPackaging => "ProcessProductPackaging",
+            StepKind::PhaseScriptExecution => "PhaseScriptExecution",
+            StepKind::Copy => "Copy",
+            StepKind::CpResource => "CpResource",
+            StepKind::CopySwiftLibs => "CopySwiftLibs",
+            StepKind::Touch => "Touch",
+            StepKind::MkDir => "MkDir",
+            StepKind::SymLink => "SymLink",
+            StepKind::WriteAuxiliaryFile => "WriteAuxiliaryFile",
+            StepKind::CreateBuildDirectory => "CreateBuildDirectory",
+            StepKind::Other(name) => name,
+        }
+    }
+
+    /// Steps running the Swift compiler, either the driver or a frontend job
+    pub fn is_swift_compile(&self) -> bool {
+        matches!(
+            self,
+            StepKind::SwiftDriver | StepKind::SwiftCompile | StepKind::CompileSwiftSources
+        )
+    }
+
+    pub fn is_clang_compile(&self) -> bool {
+        *self == StepKind::CompileC
+    }
+}
+
+impl fmt::Display for StepKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for StepKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Kind of a step together with the arguments of its signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StepInfo {
+    pub kind: StepKind,
+    /// Build variant, usually `normal`
+    pub variant: Option<String>,
+    pub architecture: Option<String>,
+    pub source_file: Option<String>,
+    /// Object file, binary or bundle the step produces
+    pub output: Option<String>,
+}
+
+/// Build variants Xcode puts in front of the architecture
+const VARIANTS: [&str; 3] = ["normal", "profile", "debug"];
+
+/// Classifies a step by its signature, e.g.
+/// `CompileC /path/File.o /path/File.m normal arm64 objective-c com.apple.compilers.llvm.clang.1_0.compiler (in target 'App' from project 'App')`.
+/// Sections without a signature are classified by the first word of their title.
+pub fn classify(section: &IDEActivityLogSection) -> StepInfo {
+    let signature = match section.signature.rfind(" (in target '") {
+        Some(end) => &section.signature[..end],
+        None => &section.signature,
+    };
+    if signature.trim().is_empty() {
+        let name = section.title.split_whitespace().next().unwrap_or_default();
+        return StepInfo {
+            kind: StepKind::from_name(name),
+            variant: None,
+            architecture: None,
+            source_file: None,
+            output: None,
+        };
+    }
+
+    let args = split_command_line(signature);
+    let kind = StepKind::from_name(args.first().map(String::as_str).unwrap_or_default());
+    let variant_position = args.iter().position(|a| VARIANTS.contains(&a.as_str()));
+    let variant = variant_position.map(|i| args[i].clone());
+    let architecture = variant_position.and_then(|i| args.get(i + 1)).cloned();
+    let arg = |i: usize| args.get(i).cloned();
+
+    let (source_file, output) = match kind {
+        StepKind::CompileC => (arg(2), arg(1)),
+        StepKind::SwiftCompile => {
+            // Sources follow the architecture, the last absolute path is the file
+            let file = args
+                .iter()
+                .skip(variant_position.map_or(1, |i| i + 2))
+                .rev()
+                .find(|a| a.starts_with('/'))
+                .cloned();
+            (file, None)
+        }
+        StepKind::Ld
+        | StepKind::Libtool
+        | StepKind::CreateUniversalBinary
+        | StepKind::CodeSign
+        | StepKind::Strip
+        | StepKind::Touch
+        | StepKind::MkDir
+        | StepKind::WriteAuxiliaryFile
+        | StepKind::CreateBuildDirectory
+        | StepKind::CopySwiftLibs => (None, arg(1)),
+        StepKind::GenerateDSYMFile
+        | StepKind::ProcessInfoPlistFile
+        | StepKind::ProcessProductPackaging
+        | StepKind::CompileAssetCatalog
+        | StepKind::LinkStoryboards
+        | StepKind::Copy
+        | StepKind::CpResource
+        | StepKind::SymLink => (arg(2), arg(1)),
+        StepKind::CompileStoryboard | StepKind::CompileXIB | StepKind::PhaseScriptExecution => {
+            (args.last().cloned().filter(|_| args.len() > 1), None)
+        }
+        _ => (None, None),
+    };
+
+    StepInfo {
+        kind,
+        variant,
+        architecture,
+        source_file,
+        output,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(title: &str, signature: &str) -> StepInfo {
+        classify(&IDEActivityLogSection::test_step(title, signature, None))
+    }
+
+    #[test]
+    fn classifies_compile_c() {
+        let info = info(
+            "Compile util.c",
+            "CompileC /Build/util.o /src/util.c normal arm64 c com.apple.compilers.llvm.clang.1_0.compiler (in target 'MyApp' from project 'MyApp')",
+        );
+        assert_eq!(info.kind, StepKind::CompileC);
+        assert_eq!(info.variant.as_deref(), Some("normal"));
+        assert_eq!(info.architecture.as_deref(), Some("arm64"));
+        assert_eq!(info.source_file.as_deref(), Some("/src/util.c"));
+        assert_eq!(info.output.as_deref(), Some("/Build/util.o"));
+    }
+
+    #[test]
+    fn classifies_swift_compile() {
+        let info = info(
+            "Compile App.swift",
+            "SwiftCompile normal arm64 Compiling\\ App.swift /src/App.swift (in target 'MyApp' from project 'MyApp')",
+        );
+        assert_eq!(info.kind, StepKind::SwiftCompile);
+        assert_eq!(info.source_file.as_deref(), Some("/src/App.swift"));
+        assert_eq!(info.output, None);
+    }
+
+    #[test]
+    fn keeps_unknown_prefix() {
+        let info = info("Do something", "FrobnicateThing /a /b normal arm64");
+        assert_eq!(info.kind, StepKind::Other("FrobnicateThing".to_string()));
+        assert_eq!(info.kind.to_string(), "FrobnicateThing");
+        assert_eq!(info.architecture.as_deref(), Some("arm64"));
+        assert_eq!(info.source_file, None);
+        assert_eq!(info.output, None);
+    }
+
+    #[test]
+    fn falls_back_to_title_without_signature() {
+        let info = info("Ld /Build/MyApp normal", " ");
+        assert_eq!(info.kind, StepKind::Ld);
+        assert_eq!(info.output, None);
+    }
+}
@@ -0,0 +1,368 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use time::OffsetDateTime;
+
+use crate::attachment::format_bytes;
+use crate::deser::XActivityLogObject;
+use crate::log_class::IDEActivityLogSection;
+use crate::log_types::DomainType;
+use crate::markdown::{escape_cell, write_table_header, ToMarkdown};
+use crate::walk::{
+    root_only_messages, section_messages, signature_project, target_section_configuration,
+    target_section_name, target_section_project, walk_sections,
+};
+
+/// Targets of the build with their timings and issues, durations are in seconds
+#[derive(Debug, Serialize)]
+pub struct BuildSummary {
+    pub title: String,
+    /// Build, clean, test or archive
+    pub domain_type: Option<DomainType>,
+    #[serde(with = "time::serde::iso8601::option")]
+    pub start: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::iso8601::option")]
+    pub end: Option<OffsetDateTime>,
+    pub wall_time: f64,
+    pub warnings: usize,
+    pub errors: usize,
+    /// In order of their start
+    pub targets: Vec<TargetSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TargetSummary {
+    pub name: String,
+    pub project: Option<String>,
+    pub configuration: Option<String>,
+    /// E.g. `framework` or `application`
+    pub product_type: Option<String>,
+    #[serde(with = "time::serde::iso8601")]
+    pub start: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    pub end: OffsetDateTime,
+    pub wall_time: f64,
+    /// Sum of the durations of the steps, above the wall time when steps ran in parallel
+    pub step_time: f64,
+    pub steps: usize,
+    /// Number of steps per kind, e.g. `SwiftCompile` or `Ld`
+    pub steps_by_kind: BTreeMap<String, usize>,
+    pub warnings: usize,
+    pub errors: usize,
+    pub cache_hits: usize,
+    /// CPU time of the steps that have task metrics, in seconds
+    pub cpu_time: f64,
+    /// Highest peak resident memory of a step in bytes
+    pub max_rss: Option<u64>,
+}
+
+impl TargetSummary {
+    fn new(name: &str, start: OffsetDateTime, end: OffsetDateTime) -> Self {
+        TargetSummary {
+            name: name.to_string(),
+            project: None,
+            configuration: None,
+            product_type: None,
+            start,
+            end,
+            wall_time: 0.0,
+            step_time: 0.0,
+            steps: 0,
+            steps_by_kind: BTreeMap::new(),
+            warnings: 0,
+            errors: 0,
+            cache_hits: 0,
+            cpu_time: 0.0,
+            max_rss: None,
+        }
+    }
+
+    fn add_step(&mut self, step: &IDEActivityLogSection) {
+        self.start = self.start.min(step.time_started_recording);
+        self.end = self.end.max(step.time_stopped_recording);
+        self.step_time += step.duration().as_seconds_f64();
+        self.steps += 1;
+        *self
+            .steps_by_kind
+            .entry(step.step_info().kind.to_string())
+            .or_default() += 1;
+        self.cache_hits += step.was_fetched_from_cache as usize;
+        if let Some(metrics) = step.task_metrics() {
+            self.cpu_time += metrics.cpu_time();
+            self.max_rss = self.max_rss.max(Some(metrics.max_rss));
+        }
+        for message in section_messages(step) {
+            self.warnings += message.is_warning() as usize;
+            self.errors += message.is_error() as usize;
+        }
+    }
+}
+
+/// Groups the steps of the log by the target they were built for.
+/// Only leaf steps are counted so that steps nested in other steps are not counted twice.
+pub fn build_summary(objects: &[XActivityLogObject]) -> BuildSummary {
+    let mut targets = Vec::<TargetSummary>::new();
+    let mut index = BTreeMap::<String, usize>::new();
+    let mut target_index = |name: &str, section: &IDEActivityLogSection| {
+        *index.entry(name.to_string()).or_insert_with(|| {
+            targets.push(TargetSummary::new(
+                name,
+                section.time_started_recording,
+                section.time_stopped_recording,
+            ));
+            targets.len() - 1
+        })
+    };
+
+    let mut updates = vec![];
+    for node in walk_sections(objects) {
+        if let Some(name) = target_section_name(node.section) {
+            updates.push((target_index(name, node.section), node.section, false));
+        } else if node.is_step() && node.section.sub_sections.is_empty() {
+            let name = node.target_name().unwrap_or("<no target>");
+            updates.push((target_index(name, node.section), node.section, true));
+        }
+    }
+    for (i, section, is_step) in updates {
+        let target = &mut targets[i];
+        if is_step {
+            target.add_step(section);
+            if target.project.is_none() {
+                target.project = signature_project(&section.signature).map(str::to_string);
+            }
+            continue;
+        }
+        if target.project.is_none() {
+            target.project = target_section_project(section).map(str::to_string);
+        }
+        if target.configuration.is_none() {
+            target.configuration = target_section_configuration(section).map(str::to_string);
+        }
+        if let DomainType::Target(product_type) = &section.domain_type {
+            target.product_type = Some(product_type.clone());
+        }
+        target.start = target.start.min(section.time_started_recording);
+        target.end = target.end.max(section.time_stopped_recording);
+    }
+    for target in targets.iter_mut() {
+        target.wall_time = (target.end - target.start).as_seconds_f64();
+    }
+    targets.sort_by_key(|t| t.start);
+
+    // Errors like a missing provisioning profile are only reported by the build itself
+    let root_messages = root_only_messages(objects);
+    let roots = objects.iter().flat_map(|o| o.root_sections());
+    let main_section = roots.clone().next();
+    let title = main_section.map(|s| s.title.clone()).unwrap_or_default();
+    let domain_type = main_section.map(|s| s.domain_type.clone());
+    let start = roots.clone().map(|s| s.time_started_recording).min();
+    let end = roots.map(|s| s.time_stopped_recording).max();
+    BuildSummary {
+        title,
+        domain_type,
+        start,
+        end,
+        wall_time: start
+            .zip(end)
+            .map(|(start, end)| (end - start).as_seconds_f64())
+            .unwrap_or_default(),
+        warnings: targets.iter().map(|t| t.warnings).sum::<usize>()
+            + root_messages.iter().filter(|(_, m)| m.is_warning()).count(),
+        errors: targets.iter().map(|t| t.errors).sum::<usize>()
+            + root_messages.iter().filter(|(_, m)| m.is_error()).count(),
+        targets,
+    }
+}
+
+fn kinds_summary(target: &TargetSummary) -> String {
+    let mut kinds = target.steps_by_kind.iter().collect::<Vec<_>>();
+    kinds.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    kinds
+        .iter()
+        .map(|(kind, count)| format!("{kind} {count}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for BuildSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {:.2}s, {} targets, {} warnings, {} errors\n",
+            self.title,
+            self.wall_time,
+            self.targets.len(),
+            self.warnings,
+            self.errors
+        )?;
+        writeln!(
+            f,
+            "  {:<32} {:<12} {:>10} {:>10} {:>10} {:>9} {:>6} {:>6} {:>8} {:>6}",
+            "Target",
+            "config",
+            "wall",
+            "steps",
+            "cpu",
+            "peak RSS",
+            "count",
+            "cached",
+            "warnings",
+            "errors"
+        )?;
+        for target in self.targets.iter() {
+            writeln!(
+                f,
+                "  {:<32} {:<12} {:>9.2}s {:>9.2}s {:>9.2}s {:>9} {:>6} {:>6} {:>8} {:>6}",
+                target.name,
+                target.configuration.as_deref().unwrap_or("-"),
+                target.wall_time,
+                target.step_time,
+                target.cpu_time,
+                target
+                    .max_rss
+                    .map(format_bytes)
+                    .unwrap_or_else(|| "-".to_string()),
+                target.steps,
+                target.cache_hits,
+                target.warnings,
+                target.errors
+            )?;
+            writeln!(f, "      {}", kinds_summary(target))?;
+        }
+        Ok(())
+    }
+}
+
+impl ToMarkdown for BuildSummary {
+    fn fmt_markdown(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "## {}\n", escape_cell(&self.title))?;
+        writeln!(
+            f,
+            "**{:.2}s**, {} targets, {} warnings, {} errors\n",
+            self.wall_time,
+            self.targets.len(),
+            self.warnings,
+            self.errors
+        )?;
+        write_table_header(
+            f,
+            &[
+                "Target",
+                "Project",
+                "Configuration",
+                "Wall time",
+                "Step time",
+                "CPU time",
+                "Peak RSS",
+                "Steps",
+                "Cache hits",
+                "Warnings",
+                "Errors",
+                "Step kinds",
+            ],
+        )?;
+        for target in self.targets.iter() {
+            writeln!(
+                f,
+                "| {} | {} | {} | {:.2}s | {:.2}s | {:.2}s | {} | {} | {} | {} | {} | {} |",
+                escape_cell(&target.name),
+                escape_cell(target.project.as_deref().unwrap_or("")),
+                escape_cell(target.configuration.as_deref().unwrap_or("")),
+                target.wall_time,
+                target.step_time,
+                target.cpu_time,
+                target.max_rss.map(format_bytes).unwrap_or_default(),
+                target.steps,
+                target.cache_hits,
+                target.warnings,
+                target.errors,
+                escape_cell(&kinds_summary(target))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_class::IDEActivityLogMessage;
+    use crate::log_types::Severity;
+
+    #[test]
+    fn groups_steps_by_target() {
+        let mut swift = IDEActivityLogSection::test_step_in(
+            "App",
+            "SwiftCompile normal arm64 /src/App.swift",
+            3,
+        )
+        .test_shifted(1);
+        swift.messages = vec![IDEActivityLogMessage::test_message(
+            "unused",
+            Severity::Warning,
+        )];
+        let mut link =
+            IDEActivityLogSection::test_step_in("App", "Ld /Build/App normal", 2).test_shifted(4);
+        link.was_fetched_from_cache = true;
+        let mut target = IDEActivityLogSection::test_target("App", vec![swift, link]);
+        target.domain_type = DomainType::Target("application".to_string());
+        // A step outside of a target section, grouped by its signature
+        let pod = IDEActivityLogSection::test_step_in(
+            "Pod",
+            "CompileC /Build/Pod.o /src/Pod.m normal arm64 objective-c com.apple.compilers.llvm.clang.1_0.compiler",
+            1,
+        );
+
+        let summary = build_summary(&[IDEActivityLogSection::test_build(vec![target, pod]).into()]);
+        assert_eq!(summary.title, "Build App");
+        assert_eq!(summary.wall_time, 6.0);
+        assert_eq!(summary.warnings, 1);
+        let names = summary
+            .targets
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Pod", "App"]);
+
+        let pod = &summary.targets[0];
+        assert_eq!(pod.project.as_deref(), Some("App"));
+        assert_eq!(pod.configuration, None);
+        assert_eq!(pod.steps, 1);
+
+        let app = &summary.targets[1];
+        assert_eq!(app.project.as_deref(), Some("App"));
+        assert_eq!(app.configuration.as_deref(), Some("Debug"));
+        assert_eq!(app.product_type.as_deref(), Some("application"));
+        assert_eq!((app.steps, app.cache_hits, app.warnings), (2, 1, 1));
+        assert_eq!((app.wall_time, app.step_time), (5.0, 5.0));
+        assert_eq!(app.steps_by_kind.get("SwiftCompile"), Some(&1));
+        assert_eq!(app.steps_by_kind.get("Ld"), Some(&1));
+    }
+
+    #[test]
+    fn counts_errors_only_reported_by_the_build() {
+        let mut step = IDEActivityLogSection::test_step_in(
+            "App",
+            "SwiftCompile normal arm64 /src/App.swift",
+            1,
+        );
+        let copied = || IDEActivityLogMessage::test_message("cannot find 'x'", Severity::Error);
+        step.messages = vec![copied()];
+        let mut build = IDEActivityLogSection::test_build(vec![step]);
+        build.messages = vec![
+            copied(),
+            IDEActivityLogMessage::test_message(
+                "No profiles for 'com.example.App'",
+                Severity::Error,
+            ),
+            IDEActivityLogMessage::test_message(
+                "Run script build phase will be run",
+                Severity::Warning,
+            ),
+        ];
+
+        let summary = build_summary(&[build.into()]);
+        assert_eq!((summary.errors, summary.warnings), (2, 1));
+        assert_eq!(summary.targets[0].errors, 1);
+    }
+}
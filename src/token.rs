@@ -1,4 +1,4 @@
-use anyhow::bail;
+use anyhow::anyhow;
 use serde_json::Value;
 use std::error::Error;
 use std::fmt;
@@ -32,18 +32,79 @@ impl Token {
     }
 }
 
-impl ToString for Token {
-    fn to_string(&self) -> String {
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Token::*;
         match self {
-            Int(v) => v.to_string(),
-            Double(v) => v.to_string(),
-            ClassName(v) => v.to_string(),
-            ClassInstance(v) => v.to_string(),
-            String(v) => v.to_string(),
-            Null => "null".to_string(),
-            Array(v) => v.to_string(),
-            Json(v) => v.to_string(),
+            Int(v) => write!(f, "{v}"),
+            Double(v) => write!(f, "{v}"),
+            ClassName(v) => write!(f, "{v}"),
+            ClassInstance(v) => write!(f, "{v}"),
+            String(v) => write!(f, "{v}"),
+            Null => write!(f, "null"),
+            Array(v) => write!(f, "{v}"),
+            Json(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// Token that borrows its text from the buffer it was scanned from.
+/// Produced by [`crate::parser::SliceParser`], convert it into [`Token`] when ownership is needed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BorrowedToken<'a> {
+    Int(u64),
+    Double(f64),
+    ClassName(&'a str),
+    ClassInstance(usize),
+    String(&'a str),
+    Null,
+    Array(usize),
+    Json(&'a str),
+}
+
+impl BorrowedToken<'_> {
+    pub fn get_type_as_str(&self) -> &str {
+        use BorrowedToken::*;
+        match self {
+            Int(_) => "int",
+            Double(_) => "double",
+            ClassName(_) => "class_name",
+            ClassInstance(_) => "class_instance",
+            String(_) => "string",
+            Null => "null",
+            Array(_) => "array",
+            Json(_) => "json",
+        }
+    }
+}
+
+impl fmt::Display for BorrowedToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use BorrowedToken::*;
+        match self {
+            Int(v) => write!(f, "{v}"),
+            Double(v) => write!(f, "{v}"),
+            ClassName(v) => write!(f, "{v}"),
+            ClassInstance(v) => write!(f, "{v}"),
+            String(v) => write!(f, "{v}"),
+            Null => write!(f, "null"),
+            Array(v) => write!(f, "{v}"),
+            Json(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+impl From<BorrowedToken<'_>> for Token {
+    fn from(value: BorrowedToken<'_>) -> Self {
+        match value {
+            BorrowedToken::Int(v) => Token::Int(v),
+            BorrowedToken::Double(v) => Token::Double(v),
+            BorrowedToken::ClassName(v) => Token::ClassName(v.to_string()),
+            BorrowedToken::ClassInstance(v) => Token::ClassInstance(v),
+            BorrowedToken::String(v) => Token::String(v.to_string()),
+            BorrowedToken::Null => Token::Null,
+            BorrowedToken::Array(v) => Token::Array(v),
+            BorrowedToken::Json(v) => Token::Json(v.to_string()),
         }
     }
 }
@@ -191,11 +252,33 @@ impl TryFrom<Token> for Value {
     }
 }
 
+/// Converts seconds since the Apple reference date (2001-01-01 UTC) into a timestamp.
+/// Xcode uses this epoch for every time stored in activity logs and their manifests.
+/// `None` for NaN, infinite or out of range values, e.g. from a corrupt log.
+pub fn datetime_from_reference_seconds(seconds: f64) -> Option<OffsetDateTime> {
+    datetime!(2001-01-01 0:00 UTC).checked_add(Duration::checked_seconds_f64(seconds)?)
+}
+
 impl TryFrom<Token> for OffsetDateTime {
     type Error = ConversionError;
     fn try_from(value: Token) -> Result<Self, Self::Error> {
         match value {
-            Token::Double(v) => Ok(datetime!(2001-01-01 0:00 UTC) + Duration::seconds(v as i64)),
+            Token::Double(v) => datetime_from_reference_seconds(v).ok_or(ConversionError {
+                from: "Token",
+                to: "DateTime<Utc>",
+                value: format!("{:?}", value),
+            }),
+            // Message times are stored as whole seconds
+            Token::Int(v) => i64::try_from(v)
+                .ok()
+                .and_then(|seconds| {
+                    datetime!(2001-01-01 0:00 UTC).checked_add(Duration::seconds(seconds))
+                })
+                .ok_or(ConversionError {
+                    from: "Token",
+                    to: "DateTime<Utc>",
+                    value: format!("{:?}", value),
+                }),
             other => Err(ConversionError {
                 from: "Token",
                 to: "DateTime<Utc>",
@@ -329,21 +412,61 @@ pub enum TokenType {
     Json,
 }
 
+impl TokenType {
+    /// Maps the byte that ends a left hand side value to its token type
+    pub fn from_byte(value: u8) -> Option<Self> {
+        use TokenType::*;
+        Some(match value {
+            b'#' => Int,
+            b'^' => Double,
+            b'-' => Null,
+            b'"' => String,
+            b'(' => Array,
+            b'%' => ClassName,
+            b'@' => ClassInstance,
+            b'*' => Json,
+            _ => return None,
+        })
+    }
+}
+
 impl TryFrom<char> for TokenType {
     type Error = anyhow::Error;
 
     fn try_from(value: char) -> Result<Self, Self::Error> {
-        use TokenType::*;
-        Ok(match value {
-            '#' => Int,
-            '^' => Double,
-            '-' => Null,
-            '"' => String,
-            '(' => Array,
-            '%' => ClassName,
-            '@' => ClassInstance,
-            '*' => Json,
-            _ => bail!("Unknown char: {}", value),
-        })
+        u8::try_from(value)
+            .ok()
+            .and_then(TokenType::from_byte)
+            .ok_or_else(|| anyhow!("Unknown char: {}", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn section_time_keeps_fractions_of_seconds() {
+        let time = OffsetDateTime::try_from(Token::Double(1.5)).unwrap();
+        assert_eq!(time, datetime!(2001-01-01 0:00:01.5 UTC));
+    }
+
+    #[test]
+    fn invalid_section_time_is_an_error() {
+        for seconds in [f64::NAN, f64::INFINITY, -f64::INFINITY, 1e300] {
+            assert!(OffsetDateTime::try_from(Token::Double(seconds)).is_err());
+        }
+    }
+
+    #[test]
+    fn message_time_in_whole_seconds() {
+        let time = OffsetDateTime::try_from(Token::Int(90)).unwrap();
+        assert_eq!(time, datetime!(2001-01-01 0:01:30 UTC));
+    }
+
+    #[test]
+    fn out_of_range_message_time_is_an_error() {
+        assert!(OffsetDateTime::try_from(Token::Int(u64::MAX)).is_err());
+        assert!(OffsetDateTime::try_from(Token::Int(i64::MAX as u64)).is_err());
     }
 }
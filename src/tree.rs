@@ -0,0 +1,401 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::deser::XActivityLogObject;
+use crate::log_class::{IDEActivityLogMessage, IDEActivityLogSection};
+use crate::walk::{root_only_messages, section_messages, SectionNode};
+
+/// Messages that only the root sections report, see [`root_only_messages`]
+type RootMessages<'a> = [(SectionNode<'a>, &'a IDEActivityLogMessage)];
+
+/// Which sections `section_tree` keeps
+#[derive(Debug, Clone, Default)]
+pub struct TreeOptions {
+    /// Deepest level shown, the top of the log being level 0
+    pub max_depth: Option<usize>,
+    /// Sections shorter than this many seconds are left out together with their children
+    pub min_duration: Option<f64>,
+    /// Only sections whose title contains this text, the sections they are nested in
+    /// and the sections nested in them
+    pub title_filter: Option<String>,
+}
+
+/// Hierarchy of the log: the build log, its targets and their steps
+#[derive(Debug, Serialize)]
+pub struct SectionTree {
+    pub roots: Vec<TreeNode>,
+}
+
+/// Section of the log tree, the duration is in seconds
+#[derive(Debug, Serialize)]
+pub struct TreeNode {
+    pub title: String,
+    pub duration: f64,
+    pub cached: bool,
+    /// Messages of the section itself, not of its children
+    pub warnings: usize,
+    pub errors: usize,
+    /// Children hidden by `max_depth`
+    pub hidden_children: usize,
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    fn new(title: &str, duration: f64, cached: bool) -> Self {
+        TreeNode {
+            title: title.to_string(),
+            duration,
+            cached,
+            warnings: 0,
+            errors: 0,
+            hidden_children: 0,
+            children: vec![],
+        }
+    }
+}
+
+fn title_matches(title: &str, options: &TreeOptions) -> bool {
+    options
+        .title_filter
+        .as_deref()
+        .is_some_and(|filter| title.contains(filter))
+}
+
+fn is_too_short(duration: f64, options: &TreeOptions) -> bool {
+    options.min_duration.is_some_and(|min| duration < min)
+}
+
+/// Whether a section nested in `sections` at any depth passes the duration and title filters,
+/// so that the section they are nested in stays even when the depth limit hides them
+fn has_nested_match(sections: &[IDEActivityLogSection], options: &TreeOptions) -> bool {
+    sections.iter().any(|section| {
+        !is_too_short(section.duration().as_seconds_f64(), options)
+            && (title_matches(&section.title, options)
+                || has_nested_match(&section.sub_sections, options))
+    })
+}
+
+/// Node of the section if it passes the filters, `None` otherwise.
+/// `matched` tells whether the title filter already matched an ancestor.
+/// `root_messages` is only given for the root sections of the log, which repeat
+/// the messages of their steps.
+fn section_node(
+    section: &IDEActivityLogSection,
+    depth: usize,
+    matched: bool,
+    root_messages: Option<&RootMessages>,
+    options: &TreeOptions,
+) -> Option<TreeNode> {
+    let duration = section.duration().as_seconds_f64();
+    if is_too_short(duration, options) {
+        return None;
+    }
+
+    let mut node = TreeNode::new(&section.title, duration, section.was_fetched_from_cache);
+    let messages = match root_messages {
+        Some(root_messages) => root_messages
+            .iter()
+            .filter(|(root, _)| std::ptr::eq(root.section, section))
+            .map(|(_, message)| *message)
+            .collect(),
+        None => section_messages(section),
+    };
+    for message in messages {
+        node.warnings += message.is_warning() as usize;
+        node.errors += message.is_error() as usize;
+    }
+    let matched = matched || options.title_filter.is_none() || title_matches(&node.title, options);
+    let children = &section.sub_sections;
+    let has_match_below = match options.max_depth {
+        Some(max_depth) if depth >= max_depth => {
+            node.hidden_children = children.len();
+            !matched && has_nested_match(children, options)
+        }
+        _ => {
+            node.children = children
+                .iter()
+                .filter_map(|child| section_node(child, depth + 1, matched, None, options))
+                .collect();
+            !node.children.is_empty()
+        }
+    };
+    (matched || has_match_below).then_some(node)
+}
+
+/// Builds the tree of sections that pass the filters of `options`
+pub fn section_tree(objects: &[XActivityLogObject], options: &TreeOptions) -> SectionTree {
+    let root_messages = root_only_messages(objects);
+    let roots = objects
+        .iter()
+        .filter_map(|object| match object {
+            XActivityLogObject::IDECommandLineBuildLog(log) => {
+                let duration =
+                    (log.time_stopped_recording - log.time_started_recording).as_seconds_f64();
+                if is_too_short(duration, options) {
+                    return None;
+                }
+                let mut node = TreeNode::new(&log.title, duration, false);
+                let matched = options.title_filter.is_none() || title_matches(&log.title, options);
+                let has_match_below = match options.max_depth {
+                    Some(0) => {
+                        node.hidden_children = log.sub_sections.len();
+                        !matched && has_nested_match(&log.sub_sections, options)
+                    }
+                    _ => {
+                        node.children = log
+                            .sub_sections
+                            .iter()
+                            .filter_map(|section| {
+                                section_node(section, 1, matched, Some(&root_messages), options)
+                            })
+                            .collect();
+                        !node.children.is_empty()
+                    }
+                };
+                (matched || has_match_below).then_some(node)
+            }
+            XActivityLogObject::IDEActivityLogSection(section) => {
+                section_node(section, 0, false, Some(&root_messages), options)
+            }
+            _ => None,
+        })
+        .collect();
+    SectionTree { roots }
+}
+
+fn write_node(
+    f: &mut fmt::Formatter<'_>,
+    node: &TreeNode,
+    prefix: &str,
+    last: bool,
+) -> fmt::Result {
+    let (branch, continuation) = match last {
+        true => ("└── ", "    "),
+        false => ("├── ", "│   "),
+    };
+    write_line(f, node, &format!("{prefix}{branch}"))?;
+    write_children(f, node, &format!("{prefix}{continuation}"))
+}
+
+fn write_line(f: &mut fmt::Formatter<'_>, node: &TreeNode, prefix: &str) -> fmt::Result {
+    write!(f, "{prefix}{} ({:.2}s", node.title, node.duration)?;
+    if node.cached {
+        write!(f, ", cached")?;
+    }
+    if node.warnings > 0 {
+        write!(f, ", {} warnings", node.warnings)?;
+    }
+    if node.errors > 0 {
+        write!(f, ", {} errors", node.errors)?;
+    }
+    writeln!(f, ")")
+}
+
+fn write_children(f: &mut fmt::Formatter<'_>, node: &TreeNode, prefix: &str) -> fmt::Result {
+    for (i, child) in node.children.iter().enumerate() {
+        let last = i + 1 == node.children.len() && node.hidden_children == 0;
+        write_node(f, child, prefix, last)?;
+    }
+    if node.hidden_children > 0 {
+        writeln!(f, "{prefix}└── ... {} hidden", node.hidden_children)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for SectionTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.roots.is_empty() {
+            return writeln!(f, "No sections match");
+        }
+        for root in self.roots.iter() {
+            write_line(f, root, "")?;
+            write_children(f, root, "")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_class::IDECommandLineBuildLog;
+    use crate::log_types::Severity;
+    use time::Duration;
+
+    fn section(
+        title: &str,
+        seconds: i64,
+        sub_sections: Vec<IDEActivityLogSection>,
+    ) -> IDEActivityLogSection {
+        let mut section = IDEActivityLogSection::test_step(title, "", None);
+        section.time_stopped_recording =
+            section.time_started_recording + Duration::seconds(seconds);
+        section.sub_sections = sub_sections;
+        section
+    }
+
+    /// Build with two targets of two steps each
+    fn build() -> Vec<XActivityLogObject> {
+        let mut compile = section("Compile A.swift", 3, vec![]);
+        compile.messages = vec![IDEActivityLogMessage::test_message(
+            "cannot find 'x' in scope",
+            Severity::Error,
+        )];
+        let app = section(
+            "Build target App",
+            5,
+            vec![compile, section("Link App", 1, vec![])],
+        );
+        let kit = section(
+            "Build target Kit",
+            2,
+            vec![
+                section("Compile K.swift", 2, vec![]),
+                section("Copy K.h", 0, vec![]),
+            ],
+        );
+        let mut build = section("Build App", 7, vec![app, kit]);
+        build.messages = vec![
+            IDEActivityLogMessage::test_message("cannot find 'x' in scope", Severity::Error),
+            IDEActivityLogMessage::test_message(
+                "No profiles for 'com.example.App'",
+                Severity::Error,
+            ),
+        ];
+        vec![XActivityLogObject::IDEActivityLogSection(build)]
+    }
+
+    /// `xcodebuild` log around the build of `build()`
+    fn command_line_log() -> XActivityLogObject {
+        let XActivityLogObject::IDEActivityLogSection(build) = build().remove(0) else {
+            unreachable!();
+        };
+        XActivityLogObject::IDECommandLineBuildLog(IDECommandLineBuildLog {
+            section_type: build.section_type,
+            domain_type: build.domain_type.clone(),
+            title: "xcodebuild".to_string(),
+            signature: String::new(),
+            time_started_recording: build.time_started_recording,
+            time_stopped_recording: build.time_started_recording + Duration::seconds(8),
+            sub_sections: vec![build],
+        })
+    }
+
+    fn titles(nodes: &[TreeNode]) -> Vec<&str> {
+        nodes.iter().map(|n| n.title.as_str()).collect()
+    }
+
+    #[test]
+    fn counts_only_errors_reported_by_the_build_itself() {
+        let tree = section_tree(&build(), &TreeOptions::default());
+        let root = &tree.roots[0];
+        assert_eq!(root.errors, 1);
+        assert_eq!(root.children[0].children[0].errors, 1);
+    }
+
+    #[test]
+    fn hides_children_below_max_depth() {
+        let options = TreeOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let tree = section_tree(&build(), &options);
+        let root = &tree.roots[0];
+        assert_eq!(
+            titles(&root.children),
+            ["Build target App", "Build target Kit"]
+        );
+        assert!(root.children.iter().all(|c| c.children.is_empty()));
+        assert_eq!(root.children[0].hidden_children, 2);
+        assert!(tree.to_string().contains("└── ... 2 hidden"));
+    }
+
+    #[test]
+    fn leaves_out_short_sections() {
+        let options = TreeOptions {
+            min_duration: Some(2.0),
+            ..Default::default()
+        };
+        let tree = section_tree(&build(), &options);
+        let root = &tree.roots[0];
+        assert_eq!(titles(&root.children[0].children), ["Compile A.swift"]);
+        assert_eq!(titles(&root.children[1].children), ["Compile K.swift"]);
+
+        let options = TreeOptions {
+            min_duration: Some(10.0),
+            ..Default::default()
+        };
+        assert!(section_tree(&build(), &options).roots.is_empty());
+    }
+
+    #[test]
+    fn applies_min_duration_to_command_line_logs() {
+        let options = |min_duration| TreeOptions {
+            min_duration: Some(min_duration),
+            ..Default::default()
+        };
+        let tree = section_tree(&[command_line_log()], &options(4.0));
+        assert_eq!(titles(&tree.roots), ["xcodebuild"]);
+        assert_eq!(titles(&tree.roots[0].children), ["Build App"]);
+        assert!(section_tree(&[command_line_log()], &options(10.0))
+            .roots
+            .is_empty());
+    }
+
+    #[test]
+    fn keeps_matches_with_their_ancestors_and_children() {
+        let options = TreeOptions {
+            title_filter: Some("Kit".to_string()),
+            ..Default::default()
+        };
+        let tree = section_tree(&build(), &options);
+        let root = &tree.roots[0];
+        assert_eq!(titles(&root.children), ["Build target Kit"]);
+        assert_eq!(
+            titles(&root.children[0].children),
+            ["Compile K.swift", "Copy K.h"]
+        );
+
+        let options = TreeOptions {
+            title_filter: Some("Link".to_string()),
+            ..Default::default()
+        };
+        let tree = section_tree(&build(), &options);
+        assert_eq!(titles(&tree.roots[0].children[0].children), ["Link App"]);
+
+        let options = TreeOptions {
+            title_filter: Some("Archive".to_string()),
+            ..Default::default()
+        };
+        let tree = section_tree(&build(), &options);
+        assert!(tree.roots.is_empty());
+        assert_eq!(tree.to_string(), "No sections match\n");
+    }
+
+    #[test]
+    fn keeps_sections_with_matches_below_max_depth() {
+        let options = TreeOptions {
+            max_depth: Some(1),
+            title_filter: Some("K.swift".to_string()),
+            ..Default::default()
+        };
+        let tree = section_tree(&build(), &options);
+        let root = &tree.roots[0];
+        assert_eq!(titles(&root.children), ["Build target Kit"]);
+        assert_eq!(root.children[0].hidden_children, 2);
+
+        let options = TreeOptions {
+            max_depth: Some(0),
+            title_filter: Some("K.swift".to_string()),
+            min_duration: Some(3.0),
+        };
+        assert!(section_tree(&build(), &options).roots.is_empty());
+        let options = TreeOptions {
+            min_duration: Some(1.0),
+            ..options
+        };
+        let tree = section_tree(&[command_line_log()], &options);
+        assert_eq!(titles(&tree.roots), ["xcodebuild"]);
+        assert_eq!(tree.roots[0].hidden_children, 1);
+    }
+}
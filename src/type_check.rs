@@ -0,0 +1,358 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::deser::XActivityLogObject;
+use crate::log_class::IDEActivityLogMessage;
+use crate::markdown::{escape_cell, write_table_header, ToMarkdown};
+use crate::walk::walk_messages;
+
+/// Slow type-checking reported by `-warn-long-function-bodies` and
+/// `-warn-long-expression-type-checking`, slowest first
+#[derive(Debug, Serialize)]
+pub struct TypeCheckReport {
+    pub warnings: Vec<TypeCheckWarning>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TypeCheckSubject {
+    FunctionBody,
+    Expression,
+}
+
+/// Warning reported for the same declaration or expression, repeated reports
+/// (e.g. one per architecture) are merged
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeCheckWarning {
+    pub subject: TypeCheckSubject,
+    /// Kind of the declaration, e.g. `Instance method`, absent for expressions
+    pub declaration_kind: Option<String>,
+    /// Name of the declaration, e.g. `foo(_:)`, absent for expressions
+    pub name: Option<String>,
+    /// Longest reported duration
+    pub duration_ms: u64,
+    pub limit_ms: u64,
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+    pub occurrences: usize,
+    pub targets: Vec<String>,
+}
+
+/// Parses a message title like
+/// `Instance method 'foo()' took 312ms to type-check (limit: 100ms)`
+/// or `Expression took 250ms to type-check (limit: 100ms)`.
+/// Returns the declaration part, the duration and the limit.
+pub fn parse_type_check_title(title: &str) -> Option<(&str, u64, u64)> {
+    let (subject, rest) = title.split_once(" took ")?;
+    let (duration, rest) = rest.split_once("ms to type-check")?;
+    let limit = rest.trim().strip_prefix("(limit: ")?.strip_suffix("ms)")?;
+    Some((
+        subject.trim(),
+        duration.trim().parse().ok()?,
+        limit.parse().ok()?,
+    ))
+}
+
+fn to_warning(message: &IDEActivityLogMessage) -> Option<TypeCheckWarning> {
+    let (subject, duration_ms, limit_ms) = parse_type_check_title(&message.title)?;
+    // swiftc writes `expression took ...`, older versions capitalized it
+    let (subject, declaration_kind, name) = match subject {
+        subject if subject.eq_ignore_ascii_case("expression") => {
+            (TypeCheckSubject::Expression, None, None)
+        }
+        subject => {
+            let (kind, name) = match subject.split_once(" '") {
+                Some((kind, name)) => (kind, Some(name.trim_end_matches('\'').to_string())),
+                None => (subject, None),
+            };
+            (TypeCheckSubject::FunctionBody, Some(kind.to_string()), name)
+        }
+    };
+    let line_and_column = message.location.as_ref().and_then(|l| l.line_and_column());
+    Some(TypeCheckWarning {
+        subject,
+        declaration_kind,
+        name,
+        duration_ms,
+        limit_ms,
+        file: message.location.as_ref().map(|l| l.file_path().to_string()),
+        line: line_and_column.map(|(line, _)| line),
+        column: line_and_column.map(|(_, column)| column),
+        occurrences: 1,
+        targets: vec![],
+    })
+}
+
+type WarningKey = (
+    TypeCheckSubject,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    u64,
+    u64,
+);
+
+/// What tells apart the reports of the same declaration or expression, `None` for warnings
+/// without a location, which can't be told apart from other warnings of the same name
+fn warning_key(warning: &TypeCheckWarning) -> Option<WarningKey> {
+    Some((
+        warning.subject,
+        warning.declaration_kind.clone(),
+        warning.name.clone(),
+        warning.file.clone(),
+        warning.line?,
+        warning.column?,
+    ))
+}
+
+/// Collects slow type-checking warnings, merges duplicates and ranks them by duration
+pub fn type_check_report(objects: &[XActivityLogObject]) -> TypeCheckReport {
+    let mut merged = HashMap::<WarningKey, TypeCheckWarning>::new();
+    let mut unlocated = vec![];
+    for (node, message) in walk_messages(objects) {
+        let Some(mut warning) = to_warning(message) else {
+            continue;
+        };
+        let entry = match warning_key(&warning) {
+            Some(key) => merged.entry(key).or_insert_with(|| TypeCheckWarning {
+                occurrences: 0,
+                ..warning.clone()
+            }),
+            None => {
+                warning.occurrences = 0;
+                unlocated.push(warning.clone());
+                unlocated.last_mut().unwrap()
+            }
+        };
+        entry.occurrences += 1;
+        entry.duration_ms = entry.duration_ms.max(warning.duration_ms);
+        if let Some(target) = node.target_name() {
+            if !entry.targets.iter().any(|t| t == target) {
+                entry.targets.push(target.to_string());
+            }
+        }
+    }
+
+    let mut warnings = merged.into_values().chain(unlocated).collect::<Vec<_>>();
+    warnings.sort_by(|a, b| {
+        b.duration_ms
+            .cmp(&a.duration_ms)
+            .then_with(|| a.file.cmp(&b.file))
+            .then_with(|| a.line.cmp(&b.line))
+    });
+    TypeCheckReport { warnings }
+}
+
+impl TypeCheckWarning {
+    fn location(&self) -> String {
+        match (&self.file, self.line, self.column) {
+            (Some(file), Some(line), Some(column)) => format!("{file}:{line}:{column}"),
+            (Some(file), _, _) => file.clone(),
+            _ => "<unknown location>".to_string(),
+        }
+    }
+
+    /// Declaration like `instance method 'foo()'`, or `Expression`
+    fn subject(&self) -> String {
+        match (&self.declaration_kind, &self.name) {
+            (Some(kind), Some(name)) => format!("{kind} '{name}'"),
+            (Some(kind), None) => kind.clone(),
+            _ => "Expression".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for TypeCheckWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>7}ms  {}  {}",
+            self.duration_ms,
+            self.subject(),
+            self.location()
+        )?;
+        if self.occurrences > 1 {
+            write!(f, "  (x{})", self.occurrences)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TypeCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Slow type-checking ({}):", self.warnings.len())?;
+        for warning in self.warnings.iter() {
+            writeln!(f, "  {warning}")?;
+        }
+        Ok(())
+    }
+}
+
+impl ToMarkdown for TypeCheckReport {
+    fn fmt_markdown(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "**Slow type-checking ({})**\n", self.warnings.len())?;
+        if self.warnings.is_empty() {
+            return Ok(());
+        }
+        write_table_header(
+            f,
+            &[
+                "Duration",
+                "Limit",
+                "Declaration",
+                "Location",
+                "Count",
+                "Targets",
+            ],
+        )?;
+        for warning in self.warnings.iter() {
+            writeln!(
+                f,
+                "| {}ms | {}ms | {} | {} | {} | {} |",
+                warning.duration_ms,
+                warning.limit_ms,
+                escape_cell(&warning.subject()),
+                escape_cell(&warning.location()),
+                warning.occurrences,
+                escape_cell(&warning.targets.join(", "))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_class::{
+        DVTBaseDocumentLocation, DVTDocumentLocation, DVTTextDocumentLocation,
+        IDEActivityLogSection,
+    };
+    use crate::log_types::Severity;
+
+    fn warning(title: &str) -> Option<TypeCheckWarning> {
+        to_warning(&IDEActivityLogMessage::test_message(
+            title,
+            Severity::Warning,
+        ))
+    }
+
+    #[test]
+    fn parses_titles() {
+        assert_eq!(
+            parse_type_check_title("expression took 250ms to type-check (limit: 100ms)"),
+            Some(("expression", 250, 100))
+        );
+        assert_eq!(
+            parse_type_check_title(
+                "Instance method 'foo()' took 312ms to type-check (limit: 100ms)"
+            ),
+            Some(("Instance method 'foo()'", 312, 100))
+        );
+        assert_eq!(parse_type_check_title("took 12ms to compile"), None);
+    }
+
+    #[test]
+    fn classifies_function_bodies() {
+        let warning =
+            warning("instance method 'foo(_:)' took 312ms to type-check (limit: 100ms)").unwrap();
+        assert_eq!(warning.subject, TypeCheckSubject::FunctionBody);
+        assert_eq!(warning.declaration_kind.as_deref(), Some("instance method"));
+        assert_eq!(warning.name.as_deref(), Some("foo(_:)"));
+        assert_eq!((warning.duration_ms, warning.limit_ms), (312, 100));
+    }
+
+    #[test]
+    fn classifies_expressions_in_any_case() {
+        for title in [
+            "expression took 250ms to type-check (limit: 100ms)",
+            "Expression took 250ms to type-check (limit: 100ms)",
+        ] {
+            let warning = warning(title).unwrap();
+            assert_eq!(warning.subject, TypeCheckSubject::Expression);
+            assert_eq!(warning.declaration_kind, None);
+            assert_eq!(warning.name, None);
+            assert_eq!(warning.duration_ms, 250);
+        }
+    }
+
+    #[test]
+    fn renders_markdown_table() {
+        let mut warning =
+            warning("instance method 'a(_:)' took 312ms to type-check (limit: 100ms)").unwrap();
+        warning.name = Some("a || b".to_string());
+        warning.targets = vec!["MyApp".to_string()];
+        let report = TypeCheckReport {
+            warnings: vec![warning],
+        };
+        let markdown = crate::markdown::Markdown(&report).to_string();
+        assert!(markdown.starts_with("**Slow type-checking (1)**\n\n| Duration |"));
+        assert!(markdown.contains(
+            "| 312ms | 100ms | instance method 'a \\|\\| b' | <unknown location> | 1 | MyApp |"
+        ));
+    }
+
+    #[test]
+    fn merges_reports_of_the_same_location_only() {
+        let message = |title: &str, line: Option<u64>| {
+            let mut message = IDEActivityLogMessage::test_message(title, Severity::Warning);
+            message.location = line.map(|line| {
+                DVTDocumentLocation::Text(DVTTextDocumentLocation {
+                    base: DVTBaseDocumentLocation {
+                        document_url_string: "file:///src/App.swift".to_string(),
+                        timestamp: 0.0,
+                    },
+                    starting_line_number: line,
+                    starting_column_number: 4,
+                    ..Default::default()
+                })
+            });
+            message
+        };
+        let mut step = IDEActivityLogSection::test_step("Compile App.swift", "", None);
+        step.messages = vec![
+            // Reported once per architecture
+            message(
+                "instance method 'a()' took 300ms to type-check (limit: 100ms)",
+                Some(9),
+            ),
+            message(
+                "instance method 'a()' took 310ms to type-check (limit: 100ms)",
+                Some(9),
+            ),
+            message(
+                "getter 'a()' took 200ms to type-check (limit: 100ms)",
+                Some(9),
+            ),
+            message("expression took 150ms to type-check (limit: 100ms)", None),
+            message("expression took 150ms to type-check (limit: 100ms)", None),
+        ];
+        let mut build = IDEActivityLogSection::test_step("Build App", "", None);
+        build.sub_sections = vec![step];
+
+        let report = type_check_report(&[XActivityLogObject::IDEActivityLogSection(build)]);
+        let rows = report
+            .warnings
+            .iter()
+            .map(|w| {
+                (
+                    w.declaration_kind.as_deref(),
+                    w.duration_ms,
+                    w.line,
+                    w.occurrences,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                (Some("instance method"), 310, Some(10), 2),
+                (Some("getter"), 200, Some(10), 1),
+                (None, 150, None, 1),
+                (None, 150, None, 1),
+            ]
+        );
+    }
+}
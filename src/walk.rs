@@ -0,0 +1,228 @@
+use std::collections::HashSet;
+
+use crate::deser::XActivityLogObject;
+use crate::log_class::{IDEActivityLogMessage, IDEActivityLogSection};
+use crate::log_types::SectionType;
+
+/// Section of the log tree together with the sections it is nested in
+#[derive(Debug, Clone)]
+pub struct SectionNode<'a> {
+    pub section: &'a IDEActivityLogSection,
+    /// From the root section down to the direct parent
+    pub ancestors: Vec<&'a IDEActivityLogSection>,
+}
+
+impl<'a> SectionNode<'a> {
+    pub fn depth(&self) -> usize {
+        self.ancestors.len()
+    }
+
+    pub fn parent(&self) -> Option<&'a IDEActivityLogSection> {
+        self.ancestors.last().copied()
+    }
+
+    /// Target that the section was built for.
+    /// Taken from the `(in target 'X' ...)` suffix of the signature if present,
+    /// otherwise from the closest `Build target X` section.
+    pub fn target_name(&self) -> Option<&'a str> {
+        std::iter::once(self.section)
+            .chain(self.ancestors.iter().rev().copied())
+            .find_map(|s| signature_target(&s.signature).or_else(|| target_section_name(s)))
+    }
+
+    /// Build configuration from the closest `Build target X ... with configuration Y` section
+    pub fn configuration(&self) -> Option<&'a str> {
+        self.ancestors
+            .iter()
+            .rev()
+            .find_map(|s| target_section_configuration(s))
+    }
+
+    /// Whether the section is a build step rather than the build itself or a target
+    pub fn is_step(&self) -> bool {
+        self.depth() > 0 && target_section_name(self.section).is_none()
+    }
+}
+
+/// Name of the target if the section groups the steps of a target.
+/// Target sections have the `Target` section type, the name comes from titles like
+/// `Build target MyApp of project MyApp with configuration Debug`.
+pub fn target_section_name(section: &IDEActivityLogSection) -> Option<&str> {
+    if section.section_type != SectionType::Target {
+        return None;
+    }
+    let rest = section.title.strip_prefix("Build target ")?;
+    let end = rest.find(" of project ").unwrap_or(rest.len());
+    Some(rest[..end].trim())
+}
+
+/// Project of a target section, `MyApp` in `Build target MyApp of project MyApp ...`
+pub fn target_section_project(section: &IDEActivityLogSection) -> Option<&str> {
+    target_section_name(section)?;
+    let (_, rest) = section.title.split_once(" of project ")?;
+    let end = rest.find(" with configuration ").unwrap_or(rest.len());
+    Some(rest[..end].trim())
+}
+
+/// Configuration of a target section, `Debug` in `Build target ... with configuration Debug`
+pub fn target_section_configuration(section: &IDEActivityLogSection) -> Option<&str> {
+    target_section_name(section)?;
+    let (_, configuration) = section.title.split_once(" with configuration ")?;
+    Some(configuration.trim())
+}
+
+/// Target mentioned in signatures like `Ld ... (in target 'MyApp' from project 'MyApp')`
+pub fn signature_target(signature: &str) -> Option<&str> {
+    let start = signature.rfind("(in target '")? + "(in target '".len();
+    let end = signature[start..].find('\'')?;
+    Some(&signature[start..start + end])
+}
+
+/// Project mentioned in signatures like `Ld ... (in target 'MyApp' from project 'MyApp')`
+pub fn signature_project(signature: &str) -> Option<&str> {
+    let start = signature.rfind("from project '")? + "from project '".len();
+    let end = signature[start..].find('\'')?;
+    Some(&signature[start..start + end])
+}
+
+/// Depth-first, pre-order traversal over all sections of the log
+pub fn walk_sections(objects: &[XActivityLogObject]) -> SectionWalk<'_> {
+    let mut stack = objects
+        .iter()
+        .flat_map(|o| o.root_sections())
+        .map(|section| SectionNode {
+            section,
+            ancestors: vec![],
+        })
+        .collect::<Vec<_>>();
+    stack.reverse();
+    SectionWalk { stack }
+}
+
+pub struct SectionWalk<'a> {
+    stack: Vec<SectionNode<'a>>,
+}
+
+impl<'a> Iterator for SectionWalk<'a> {
+    type Item = SectionNode<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        for sub_section in node.section.sub_sections.iter().rev() {
+            let mut ancestors = node.ancestors.clone();
+            ancestors.push(node.section);
+            self.stack.push(SectionNode {
+                section: sub_section,
+                ancestors,
+            });
+        }
+        Some(node)
+    }
+}
+
+/// Messages of a section including nested sub messages
+pub fn section_messages(section: &IDEActivityLogSection) -> Vec<&IDEActivityLogMessage> {
+    fn collect<'a>(
+        messages: &'a [IDEActivityLogMessage],
+        out: &mut Vec<&'a IDEActivityLogMessage>,
+    ) {
+        for message in messages {
+            out.push(message);
+            collect(&message.sub_messages, out);
+        }
+    }
+
+    let mut messages = vec![];
+    collect(&section.messages, &mut messages);
+    messages
+}
+
+/// Messages of all sections below the root ones, together with the section that emitted them.
+/// Messages of the root sections are skipped since they repeat the messages of the steps.
+pub fn walk_messages(
+    objects: &[XActivityLogObject],
+) -> impl Iterator<Item = (SectionNode<'_>, &IDEActivityLogMessage)> {
+    walk_sections(objects)
+        .filter(|node| node.depth() > 0)
+        .flat_map(|node| {
+            section_messages(node.section)
+                .into_iter()
+                .map(move |message| (node.clone(), message))
+        })
+}
+
+/// Identity of a message that survives the copy from a step to the build
+fn message_key(message: &IDEActivityLogMessage) -> (&str, Option<&str>, Option<(u64, u64)>) {
+    let location = message.location.as_ref();
+    (
+        &message.title,
+        location.map(|l| l.file_path()),
+        location.and_then(|l| l.line_and_column()),
+    )
+}
+
+/// Messages of the root sections that don't repeat a message of a step.
+/// Some errors, like a missing scheme or provisioning profile, are only reported by the build itself.
+pub fn root_only_messages(
+    objects: &[XActivityLogObject],
+) -> Vec<(SectionNode<'_>, &IDEActivityLogMessage)> {
+    let step_messages = walk_messages(objects)
+        .map(|(_, message)| message_key(message))
+        .collect::<HashSet<_>>();
+    walk_sections(objects)
+        .filter(|node| node.depth() == 0)
+        .flat_map(|node| {
+            section_messages(node.section)
+                .into_iter()
+                .map(move |message| (node.clone(), message))
+        })
+        .filter(|(_, message)| !step_messages.contains(&message_key(message)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_target_section_titles() {
+        let details = |title: &str| {
+            let mut section = IDEActivityLogSection::test_step(title, "", None);
+            section.section_type = SectionType::Target;
+            (
+                target_section_name(&section).map(str::to_string),
+                target_section_project(&section).map(str::to_string),
+                target_section_configuration(&section).map(str::to_string),
+            )
+        };
+        let owned = |name: &str, project: &str, configuration: Option<&str>| {
+            (
+                Some(name.to_string()),
+                Some(project.to_string()),
+                configuration.map(str::to_string),
+            )
+        };
+        assert_eq!(
+            details("Build target MyApp of project MyApp with configuration Debug"),
+            owned("MyApp", "MyApp", Some("Debug"))
+        );
+        assert_eq!(
+            details("Build target Core of project My App with configuration Release Staging"),
+            owned("Core", "My App", Some("Release Staging"))
+        );
+        assert_eq!(
+            details("Build target Core of project Shared"),
+            owned("Core", "Shared", None)
+        );
+        assert_eq!(details("Compile App.swift"), (None, None, None));
+    }
+
+    #[test]
+    fn parses_signature_targets() {
+        let signature = "Ld /Build/MyApp normal (in target 'MyApp' from project 'My Project')";
+        assert_eq!(signature_target(signature), Some("MyApp"));
+        assert_eq!(signature_project(signature), Some("My Project"));
+        assert_eq!(signature_target("Touch /Build/MyApp"), None);
+        assert_eq!(signature_project("Touch /Build/MyApp"), None);
+    }
+}
@@ -0,0 +1,255 @@
+use serde::Serialize;
+use std::fmt;
+use time::OffsetDateTime;
+
+use crate::command::BuildCommand;
+use crate::deser::XActivityLogObject;
+use crate::log_class::{IDEActivityLogMessage, IDEActivityLogSection};
+use crate::step_kind::StepKind;
+use crate::walk::{root_only_messages, walk_messages, SectionNode};
+
+/// Lines of step output shown before and after the lines of the error
+const CONTEXT_LINES: usize = 3;
+
+/// Lines from the end of the step output shown when the error can't be found in it
+const OUTPUT_TAIL_LINES: usize = 20;
+
+/// Where a failed build went wrong: the first error, the step that reported it
+/// and the errors that followed, which are often caused by the first one
+#[derive(Debug, Serialize)]
+pub struct FailureReport {
+    pub errors: usize,
+    pub first_error: Option<FailedStep>,
+    /// Errors emitted after the first one, in order of emission
+    pub cascading_errors: Vec<ErrorSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailedStep {
+    pub error: ErrorSummary,
+    pub kind: Option<StepKind>,
+    pub signature: Option<String>,
+    pub command: Option<BuildCommand>,
+    /// Part of the step output around the error
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorSummary {
+    pub target: Option<String>,
+    /// Title of the step that reported the error
+    pub step: Option<String>,
+    pub title: String,
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+    #[serde(with = "time::serde::iso8601")]
+    pub time_emitted: OffsetDateTime,
+}
+
+impl ErrorSummary {
+    fn new(node: &SectionNode, message: &IDEActivityLogMessage) -> Self {
+        let line_and_column = message.location.as_ref().and_then(|l| l.line_and_column());
+        ErrorSummary {
+            target: node.target_name().map(str::to_string),
+            step: node.is_step().then(|| node.section.title.clone()),
+            title: message.title.clone(),
+            file: message
+                .location
+                .as_ref()
+                .map(|l| l.file_path().to_string())
+                .filter(|path| !path.is_empty()),
+            line: line_and_column.map(|(line, _)| line),
+            column: line_and_column.map(|(_, column)| column),
+            time_emitted: message.time_emitted,
+        }
+    }
+}
+
+/// Lines of `text` from `first` to `last` with some context around them
+fn lines_around(lines: &[&str], first: usize, last: usize) -> String {
+    let start = first.saturating_sub(CONTEXT_LINES);
+    let end = (last + CONTEXT_LINES + 1).min(lines.len());
+    lines[start..end].join("\n")
+}
+
+/// The step output the error was produced from: the lines of the message range if it
+/// is known, otherwise the lines mentioning the error, otherwise the end of the output
+fn relevant_output(
+    section: &IDEActivityLogSection,
+    message: &IDEActivityLogMessage,
+) -> Option<String> {
+    let text = section.text.as_deref()?;
+    let lines = text.lines().collect::<Vec<_>>();
+    if lines.is_empty() {
+        return None;
+    }
+
+    let snippet_lines = message
+        .section_text
+        .as_deref()
+        .map(|snippet| {
+            snippet
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let matching = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| match snippet_lines.is_empty() {
+            true => line.contains(&message.title) || line.contains("error:"),
+            false => snippet_lines.iter().any(|s| line.contains(s.trim())),
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    match (matching.first(), matching.last()) {
+        (Some(first), Some(last)) => Some(lines_around(&lines, *first, *last)),
+        _ => Some(lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n")),
+    }
+}
+
+/// Finds the first error of the build and the step that reported it
+pub fn failure_report(objects: &[XActivityLogObject]) -> FailureReport {
+    let mut errors = walk_messages(objects)
+        .filter(|(_, message)| message.is_error())
+        .collect::<Vec<_>>();
+    errors.extend(
+        root_only_messages(objects)
+            .into_iter()
+            .filter(|(_, message)| message.is_error()),
+    );
+    // Stable, so errors emitted in the same second keep the order of the log
+    errors.sort_by_key(|(_, message)| message.time_emitted);
+
+    let mut errors_iter = errors.iter();
+    let first_error = errors_iter.next().map(|(node, message)| {
+        let is_step = node.is_step();
+        FailedStep {
+            error: ErrorSummary::new(node, message),
+            kind: is_step.then(|| node.section.step_info().kind),
+            signature: is_step.then(|| node.section.signature.clone()),
+            command: is_step.then(|| node.section.build_command()).flatten(),
+            output: is_step
+                .then(|| relevant_output(node.section, message))
+                .flatten(),
+        }
+    });
+    FailureReport {
+        errors: errors.len(),
+        first_error,
+        cascading_errors: errors_iter
+            .map(|(node, message)| ErrorSummary::new(node, message))
+            .collect(),
+    }
+}
+
+impl fmt::Display for ErrorSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] ", self.target.as_deref().unwrap_or("<no target>"))?;
+        match (&self.file, self.line, self.column) {
+            (Some(file), Some(line), Some(column)) => write!(f, "{file}:{line}:{column}: ")?,
+            (Some(file), _, _) => write!(f, "{file}: ")?,
+            _ => {}
+        }
+        write!(f, "{}", self.title)
+    }
+}
+
+impl fmt::Display for FailureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(failed) = &self.first_error else {
+            return writeln!(f, "No errors found in the log");
+        };
+        writeln!(f, "{} errors, the first one:\n", self.errors)?;
+        writeln!(f, "  {}", failed.error)?;
+        if let Some(step) = &failed.error.step {
+            let kind = failed.kind.as_ref().map(StepKind::as_str).unwrap_or("?");
+            writeln!(f, "  in step {step} ({kind})")?;
+        }
+
+        if let Some(command) = &failed.command {
+            writeln!(f, "\nCommand:\n  {command}")?;
+        }
+        if let Some(output) = &failed.output {
+            writeln!(f, "\nOutput:")?;
+            for line in output.lines() {
+                writeln!(f, "  | {line}")?;
+            }
+        }
+
+        if !self.cascading_errors.is_empty() {
+            writeln!(
+                f,
+                "\nLater errors, possibly caused by the first one ({}):",
+                self.cascading_errors.len()
+            )?;
+            for error in self.cascading_errors.iter() {
+                writeln!(f, "  {error}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_types::Severity;
+
+    fn error(title: &str) -> IDEActivityLogMessage {
+        IDEActivityLogMessage::test_message(title, Severity::Error)
+    }
+
+    #[test]
+    fn reports_errors_of_the_build_itself() {
+        let mut build = IDEActivityLogSection::test_build(vec![]);
+        build.messages = vec![error("No profiles for 'com.example.MyApp' were found")];
+        let report = failure_report(&[build.into()]);
+        assert_eq!(report.errors, 1);
+        let failed = report.first_error.unwrap();
+        assert_eq!(
+            failed.error.title,
+            "No profiles for 'com.example.MyApp' were found"
+        );
+        assert_eq!(failed.error.step, None);
+        assert_eq!(failed.kind, None);
+    }
+
+    #[test]
+    fn takes_no_command_or_output_from_the_build() {
+        let mut build = IDEActivityLogSection::test_build(vec![]);
+        build.text = Some(
+            "cd /src\n/usr/bin/xcodebuild -scheme MyApp\nerror: Scheme MyApp is not configured\n"
+                .to_string(),
+        );
+        build.messages = vec![error("Scheme MyApp is not configured")];
+
+        let failed = failure_report(&[build.into()]).first_error.unwrap();
+        assert_eq!(failed.error.title, "Scheme MyApp is not configured");
+        assert_eq!(failed.signature, None);
+        assert!(failed.command.is_none());
+        assert_eq!(failed.output, None);
+    }
+
+    #[test]
+    fn skips_build_copies_of_step_errors() {
+        let mut step = IDEActivityLogSection::test_step(
+            "Compile util.c",
+            "CompileC /Build/util.o /src/util.c normal arm64 c com.apple.compilers.llvm.clang.1_0.compiler",
+            Some("/src/util.c:1:1: error: expected ';'\n"),
+        );
+        step.messages = vec![error("expected ';'")];
+        let mut build = IDEActivityLogSection::test_build(vec![step]);
+        build.messages = vec![error("expected ';'"), error("Command CompileC failed")];
+        let report = failure_report(&[build.into()]);
+        assert_eq!(report.errors, 2);
+        let failed = report.first_error.unwrap();
+        assert_eq!(failed.error.title, "expected ';'");
+        assert_eq!(failed.error.step.as_deref(), Some("Compile util.c"));
+        assert_eq!(failed.kind, Some(StepKind::CompileC));
+        assert_eq!(report.cascading_errors.len(), 1);
+        assert_eq!(report.cascading_errors[0].title, "Command CompileC failed");
+    }
+}